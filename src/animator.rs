@@ -6,7 +6,7 @@ use crate::{GameState, NotReady};
 // ---
 
 #[derive(Component)]
-pub struct  CurrentAnimation(pub usize, Entity);
+pub struct  CurrentAnimation(pub usize, pub(crate) Entity);
 
 pub struct AnimationSet {
     pub animations: Vec<AnimationNodeIndex>,
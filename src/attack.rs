@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ron_asset::RonAssetPlugin;
+use crate::shared::ScriptedDeployment;
+use crate::trebuchet::TrebuchetSpawn;
+use crate::trebuchet_config::TrebuchetConfigOverrides;
+use crate::NotReady;
+
+// ---
+
+/// Lines the trebuchets up from a `*.attack.ron` layout: a formation, the config each unit
+/// uses and when each one makes its first throw.
+pub struct AttackPlugin;
+impl Plugin for AttackPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins(RonAssetPlugin::<AttackLayout>::new(&["attack.ron"]))
+        .add_systems(Startup, startup.run_if(not(resource_exists::<ScriptedDeployment>)))
+        .add_systems(Update, deploy.run_if(any_with_component::<AttackTMP>))
+        ;
+    }
+}
+
+// ---
+
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct AttackLayout {
+    pub formation: Formation,
+    /// Config file per unit index, the default config for the rest.
+    #[serde(default)]
+    pub configs: HashMap<usize, String>,
+    /// Random first throws, like every later one, if missing.
+    #[serde(default)]
+    pub schedule: Option<FireSchedule>,
+}
+
+/// Where the units stand. Yaw is in degrees, 0 faces -Z (toward the fortress).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Formation {
+    /// Side by side along X, centered on `center`.
+    Line {
+        count: usize,
+        center: Vec3,
+        spacing: f32,
+        #[serde(default)]
+        yaw: f32,
+    },
+    /// Along a circle around `center`, each unit facing it. `span` is the arc's angle in degrees.
+    Arc {
+        count: usize,
+        center: Vec3,
+        radius: f32,
+        span: f32,
+    },
+    /// A V with its tip at `tip`, each rank `depth` behind the one before and `spacing` wider.
+    Wedge {
+        count: usize,
+        tip: Vec3,
+        spacing: f32,
+        depth: f32,
+        #[serde(default)]
+        yaw: f32,
+    },
+    /// Every unit placed by hand.
+    Custom(Vec<UnitPlacement>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnitPlacement {
+    pub pos: Vec3,
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+/// When each unit makes its first throw; after that it reloads at its own pace.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FireSchedule {
+    /// Seconds into the siege of the first throw.
+    pub start: f32,
+    /// Seconds between one unit's first throw and the next's.
+    pub stagger: f32,
+    pub order: FireOrder,
+}
+
+impl Default for FireSchedule {
+    fn default() -> Self {
+        Self { start: 1., stagger: 2., order: FireOrder::Index }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub enum FireOrder {
+    /// As the units are listed.
+    #[default]
+    Index,
+    /// Nearest the middle of the formation first, then outward.
+    CenterOut,
+}
+
+impl Default for AttackLayout {
+    fn default() -> Self {
+        Self {
+            formation: Formation::Line { count: 11, center: Vec3::new(0., 0.1, 40.), spacing: 10., yaw: 0. },
+            configs: HashMap::new(),
+            schedule: None,
+        }
+    }
+}
+
+impl Formation {
+    /// Transforms of the units, by index.
+    pub fn transforms(&self) -> Vec<Transform> {
+        let at = |pos: Vec3, yaw: f32| Transform::from_translation(pos).with_rotation(Quat::from_rotation_y(yaw.to_radians()));
+        match self {
+            Self::Line { count, center, spacing, yaw } => {
+                let rot = Quat::from_rotation_y(yaw.to_radians());
+                (0..*count).map(|i| {
+                    let x = (i as f32 - (*count as f32 - 1.) * 0.5) * spacing;
+                    at(center + rot * Vec3::X * x, *yaw)
+                })
+                .collect()
+            },
+            Self::Arc { count, center, radius, span } => {
+                (0..*count).map(|i| {
+                    let t = if *count > 1 {i as f32 / (*count as f32 - 1.) - 0.5} else {0.};
+                    let angle = (t * span).to_radians();
+                    // on the +Z side of `center`, turned so -Z points back at it
+                    at(center + Vec3::new(angle.sin(), 0., angle.cos()) * *radius, angle.to_degrees())
+                })
+                .collect()
+            },
+            Self::Wedge { count, tip, spacing, depth, yaw } => {
+                let rot = Quat::from_rotation_y(yaw.to_radians());
+                (0..*count).map(|i| {
+                    // 0 is the tip, then pairs left and right one rank further back
+                    let rank = i.div_ceil(2) as f32;
+                    let side = if i % 2 == 1 {-1.} else {1.};
+                    at(tip + rot * Vec3::new(side * rank * spacing * 0.5, 0., rank * depth), *yaw)
+                })
+                .collect()
+            },
+            Self::Custom(units) => units.iter().map(|u| at(u.pos, u.yaw)).collect(),
+        }
+    }
+}
+
+impl FireSchedule {
+    /// Seconds into the siege of each unit's first throw.
+    pub fn first_throws(&self, transforms: &[Transform]) -> Vec<f32> {
+        let mut order: Vec<usize> = (0..transforms.len()).collect();
+        if let FireOrder::CenterOut = self.order {
+            let middle = transforms.iter().map(|t| t.translation).sum::<Vec3>() / transforms.len().max(1) as f32;
+            order.sort_by(|a, b| {
+                let da = transforms[*a].translation.distance_squared(middle);
+                let db = transforms[*b].translation.distance_squared(middle);
+                da.total_cmp(&db).then(a.cmp(b))
+            });
+        }
+        let mut secs = vec![0.; transforms.len()];
+        for (n, i) in order.into_iter().enumerate() {
+            secs[i] = self.start + self.stagger * n as f32;
+        }
+        secs
+    }
+}
+
+#[derive(Resource)]
+pub struct AttackLayoutHandle(pub Handle<AttackLayout>);
+
+pub const ATTACK_LAYOUT_PATH: &str = "configs/default.attack.ron";
+
+#[derive(Component)]
+struct AttackTMP;
+
+// ---
+
+fn startup(
+    mut cmd: Commands,
+    assets: Res<AssetServer>,
+) {
+    cmd.insert_resource(AttackLayoutHandle(assets.load(ATTACK_LAYOUT_PATH)));
+    cmd.spawn((NotReady, AttackTMP));
+}
+
+// ---
+
+fn deploy(
+    mut cmd: Commands,
+    handle: Res<AttackLayoutHandle>,
+    layouts: Res<Assets<AttackLayout>>,
+    assets: Res<AssetServer>,
+    overrides: Res<TrebuchetConfigOverrides>,
+    tmp_q: Single<Entity, With<AttackTMP>>,
+) {
+    let layout = match layouts.get(&handle.0) {
+        Some(l) => l.clone(),
+        None if assets.load_state(&handle.0).is_failed() => {
+            warn!("No attack layout at {ATTACK_LAYOUT_PATH}, using the built-in one");
+            AttackLayout::default()
+        },
+        None => return,
+    };
+
+    let transforms = layout.formation.transforms();
+    let first_throws = layout.schedule.as_ref().map(|s| s.first_throws(&transforms));
+    for (i, transform) in transforms.iter().enumerate() {
+        // `TrebuchetConfigOverrides` still wins, so code can swap a unit's config
+        let config = overrides.0.get(&i).or_else(|| layout.configs.get(&i));
+        cmd.trigger(TrebuchetSpawn {
+            transform: *transform,
+            config: config.map(|path| assets.load(path.clone())),
+            first_throw: first_throws.as_ref().map(|t| t[i]),
+        });
+    }
+
+    info!("Attack deployed: {} trebuchets", transforms.len());
+    cmd.entity(tmp_q.into_inner()).despawn();
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(count: usize) -> Formation {
+        Formation::Line { count, center: Vec3::new(0., 0., 40.), spacing: 10., yaw: 0. }
+    }
+
+    #[test]
+    fn line_is_centered() {
+        let xs: Vec<f32> = line(3).transforms().iter().map(|t| t.translation.x).collect();
+        assert_eq!(xs, vec![-10., 0., 10.]);
+    }
+
+    #[test]
+    fn wedge_ranks_pair_up_behind_the_tip() {
+        let wedge = Formation::Wedge { count: 5, tip: Vec3::ZERO, spacing: 10., depth: 5., yaw: 0. };
+        let at: Vec<Vec3> = wedge.transforms().iter().map(|t| t.translation).collect();
+        assert_eq!(at, vec![
+            Vec3::ZERO,
+            Vec3::new(-5., 0., 5.),
+            Vec3::new(5., 0., 5.),
+            Vec3::new(-10., 0., 10.),
+            Vec3::new(10., 0., 10.),
+        ]);
+    }
+
+    #[test]
+    fn arc_units_face_the_center() {
+        let arc = Formation::Arc { count: 3, center: Vec3::ZERO, radius: 100., span: 90. };
+        for t in arc.transforms() {
+            assert!((t.translation.length() - 100.).abs() < 1e-3);
+            assert!(t.forward().dot(-t.translation.normalize()) > 0.999);
+        }
+    }
+
+    #[test]
+    fn schedule_follows_the_index() {
+        let schedule = FireSchedule::default();
+        assert_eq!(schedule.first_throws(&line(3).transforms()), vec![1., 3., 5.]);
+    }
+
+    #[test]
+    fn schedule_goes_center_out() {
+        let schedule = FireSchedule { order: FireOrder::CenterOut, ..default() };
+        assert_eq!(schedule.first_throws(&line(5).transforms()), vec![7., 3., 1., 5., 9.]);
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+
+use crate::field::FortressPosition;
+use crate::game::MatchClock;
+use crate::projectle::BallImpact;
+use crate::shared::{Ammo, Interval};
+use crate::trebuchet::{Commanded, HoldFire, StateIdle, Trebuchet, TrebuchetTarget};
+use crate::GameState;
+
+// ---
+
+/// The attacking crews' brains. Each trebuchet aims at the fortress and walks its shots in:
+/// every landing moves its aim point against the miss. Throws are held back and let go in
+/// volleys, spread from both flanks, so the radars see many contacts at once.
+pub struct AttackerAiPlugin;
+impl Plugin for AttackerAiPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<VolleyPlanner>()
+        .add_systems(FixedUpdate, steer.run_if(resource_exists::<FortressPosition>).run_if(not(in_state(GameState::Loading))))
+        .add_systems(FixedUpdate, plan_volleys.run_if(in_state(GameState::Siege)))
+        .add_observer(enlist)
+        .add_observer(spot)
+        .add_observer(hold)
+        ;
+    }
+}
+
+// ---
+
+/// What a trebuchet's crew has learned from its landings.
+#[derive(Component, Default, Debug)]
+pub struct Spotter {
+    /// Added to the fortress position to get the aim point.
+    pub offset: Vec3,
+    /// Horizontal distance from the fortress of the last landing.
+    pub last_miss: Option<f32>,
+    pub landings: u32,
+    /// Times the trebuchet has gone idle; the first is the start of the siege.
+    idles: u32,
+}
+
+/// Gathers trebuchets whose interval is up and lets them throw together.
+#[derive(Resource, Debug)]
+pub struct VolleyPlanner {
+    /// Trebuchets a volley waits for, fewer if fewer have ammo.
+    pub min_size: usize,
+    /// Longest a ready trebuchet is held, seconds.
+    pub max_hold: f32,
+    /// Seconds between throws within a volley.
+    pub spacing: f32,
+    waiting_since: Option<f32>,
+    /// (siege seconds, trebuchet) of throws in the current volley not let go yet.
+    queue: Vec<(f32, Entity)>,
+}
+
+impl Default for VolleyPlanner {
+    fn default() -> Self {
+        Self { min_size: 4, max_hold: 6., spacing: 0.4, waiting_since: None, queue: Vec::new() }
+    }
+}
+
+/// Share of a miss corrected after each landing.
+const CORRECTION_GAIN: f32 = 0.7;
+/// Farthest the aim point moves from the fortress.
+const MAX_OFFSET: f32 = 60.;
+/// Aim point change below which the current `TrebuchetTarget` is kept.
+const RETARGET_EPSILON: f32 = 0.5;
+
+// ---
+
+fn enlist(
+    tr: Trigger<OnAdd, Trebuchet>,
+    mut cmd: Commands,
+) {
+    cmd.entity(tr.entity()).insert(Spotter::default());
+}
+
+// ---
+
+fn spot(
+    tr: Trigger<BallImpact>,
+    mut spotter_q: Query<&mut Spotter>,
+    ftp: Option<Res<FortressPosition>>,
+) {
+    let (Some(ftp), Ok(mut spotter)) = (ftp, spotter_q.get_mut(tr.event().owner)) else {
+        return;
+    };
+    // a ball on the fortress is on target, however far from its center it struck
+    let miss = if tr.event().on_fortress {Vec3::ZERO} else {(ftp.0 - tr.event().pos).with_y(0.)};
+    spotter.offset = (spotter.offset + miss * CORRECTION_GAIN).clamp_length_max(MAX_OFFSET);
+    spotter.last_miss = Some(miss.length());
+    spotter.landings += 1;
+}
+
+// ---
+
+fn steer(
+    treb_q: Query<(Entity, &Spotter, Option<&TrebuchetTarget>), Without<Commanded>>,
+    ftp: Res<FortressPosition>,
+    mut cmd: Commands,
+) {
+    for (treb_e, spotter, target) in &treb_q {
+        let aim = ftp.0.with_y(0.) + spotter.offset;
+        if target.is_none_or(|t| t.0.distance(aim) > RETARGET_EPSILON) {
+            cmd.entity(treb_e).insert(TrebuchetTarget(aim));
+        }
+    }
+}
+
+// ---
+
+fn hold(
+    tr: Trigger<OnAdd, StateIdle>,
+    mut spotter_q: Query<&mut Spotter, Without<Commanded>>,
+    mut cmd: Commands,
+) {
+    let Ok(mut spotter) = spotter_q.get_mut(tr.entity()) else {
+        return;
+    };
+    spotter.idles += 1;
+    // the opening throws follow the attack layout's schedule
+    if spotter.idles > 1 {
+        cmd.entity(tr.entity()).insert(HoldFire);
+    }
+}
+
+// ---
+
+fn plan_volleys(
+    treb_q: Query<(Entity, &Transform, Option<&Interval>, Option<&Ammo>, Has<HoldFire>), (With<Spotter>, Without<Commanded>)>,
+    mut planner: ResMut<VolleyPlanner>,
+    clock: Res<MatchClock>,
+    mut cmd: Commands,
+) {
+    let now = clock.elapsed;
+
+    let mut due = Vec::new();
+    planner.queue.retain(|(at, e)| {
+        if *at <= now {
+            due.push(*e);
+        }
+        *at > now
+    });
+    for e in due {
+        if let Some(mut ec) = cmd.get_entity(e) {
+            ec.remove::<HoldFire>();
+        }
+    }
+
+    let armed = treb_q.iter().filter(|(.., ammo, _)| !ammo.is_some_and(|a| a.0 == 0)).count();
+    let mut ready: Vec<_> = treb_q.iter()
+        .filter(|(e, _, interval, _, held)| {
+            *held && interval.is_some_and(|i| i.0.finished()) && !planner.queue.iter().any(|(_, q)| q == e)
+        })
+        .map(|(e, t, ..)| (e, t.translation.x))
+        .collect();
+    if ready.is_empty() {
+        planner.waiting_since = None;
+        return;
+    }
+    let since = *planner.waiting_since.get_or_insert(now);
+    if ready.len() < planner.min_size.min(armed) && now - since < planner.max_hold {
+        return;
+    }
+
+    // alternate the flanks so contacts come in across both radars' sectors
+    ready.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    let spacing = planner.spacing;
+    let (mut lo, mut hi) = (0, ready.len());
+    let mut n = 0;
+    while lo < hi {
+        let e = if n % 2 == 0 { lo += 1; ready[lo - 1].0 } else { hi -= 1; ready[hi].0 };
+        planner.queue.push((now + spacing * n as f32, e));
+        n += 1;
+    }
+    planner.waiting_since = None;
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::rng::SiegeRng;
+
+// ---
+
+/// Where to point a projectile leaving `origin` at `speed` so it meets a target that is at
+/// `target_pos` moving with `target_vel`. `relative_accel` is the target's acceleration minus
+/// the projectile's (zero when both just fall under gravity).
+/// Returns the aim point and the time of flight, or `None` if the projectile can't catch up.
+pub fn intercept(
+    origin: Vec3,
+    speed: f32,
+    target_pos: Vec3,
+    target_vel: Vec3,
+    relative_accel: Vec3,
+) -> Option<(Vec3, f32)> {
+    const ITERATIONS: usize = 8;
+    let mut t = origin.distance(target_pos) / speed;
+    for _ in 0..ITERATIONS {
+        let offset = target_pos - origin + target_vel * t + 0.5 * relative_accel * t * t;
+        let next_t = offset.length() / speed;
+        if !next_t.is_finite() {
+            return None;
+        }
+        t = next_t;
+    }
+    let offset = target_pos - origin + target_vel * t + 0.5 * relative_accel * t * t;
+    // the fixed point only exists if the projectile covers the distance in that time
+    if (offset.length() / speed - t).abs() > 0.01 * t.max(0.1) {
+        return None;
+    }
+    Some((origin + offset, t))
+}
+
+// ---
+
+/// `dir` deviated by a normally distributed angle with standard deviation `sigma` (radians).
+pub fn disperse(dir: Dir3, sigma: f32, rng: &mut SiegeRng) -> Dir3 {
+    let (u, v) = dir.any_orthonormal_pair();
+    let (a, b) = rng.gaussian_pair();
+    Dir3::new(*dir + (u * a + v * b) * sigma).unwrap_or(dir)
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intercept_meets_a_moving_target() {
+        let speed = 100.;
+        let target_pos = Vec3::new(200., 50., 0.);
+        let target_vel = Vec3::new(0., 0., 20.);
+        let (aim, t) = intercept(Vec3::ZERO, speed, target_pos, target_vel, Vec3::ZERO).unwrap();
+        // the projectile reaches the aim point when the target does
+        assert!((aim.length() / speed - t).abs() < 1e-3);
+        assert!(aim.distance(target_pos + target_vel * t) < 1e-3);
+    }
+
+    #[test]
+    fn intercept_leads_a_falling_target() {
+        let accel = Vec3::new(0., -9.81, 0.);
+        let (aim, t) = intercept(Vec3::ZERO, 150., Vec3::new(0., 100., 300.), Vec3::ZERO, accel).unwrap();
+        assert!(aim.distance(Vec3::new(0., 100., 300.) + 0.5 * accel * t * t) < 1e-2);
+        assert!(aim.y < 100.);
+    }
+
+    #[test]
+    fn intercept_gives_up_on_a_faster_target() {
+        assert!(intercept(Vec3::ZERO, 10., Vec3::new(100., 0., 0.), Vec3::new(50., 0., 0.), Vec3::ZERO).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::Write as _;
+
+use avian3d::prelude::LinearVelocity;
+use bevy::{app::AppExit, prelude::*};
+
+use crate::attack_ai::AttackerAiPlugin;
+use crate::defense::DefensePlugin;
+use crate::fortress::FortressPlugin;
+use crate::game::MatchRules;
+use crate::projectle::{Ball, ProjectleKey, ProjectleOwner, ProjectleRegistry, Released, BALL_RADIUS};
+use crate::radar::RadarPlugin;
+use crate::range_table::{RangeEntry, RangeTable};
+use crate::shared::ScriptedDeployment;
+use crate::sim::{headless_siege, HeadlessPlugin};
+use crate::trebuchet::{Trebuchet, TrebuchetSpawn};
+use crate::trebuchet_config::{DefaultTrebuchetConfig, TrebuchetConfig, TrebuchetConfigHandle};
+use crate::turret::TurretPlugin;
+use crate::{GameState, NotReady};
+
+// ---
+
+/// Throws once with every combination in `CalibrationGrid`, headless, and writes where the
+/// balls landed as a CSV and as the range table the aiming code reads.
+pub struct CalibrationPlugin;
+impl Plugin for CalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(ScriptedDeployment)
+        .insert_resource(MatchRules {
+            countdown: 5.,
+            time_limit: 120.,
+            ammo_per_trebuchet: Some(1),
+            ..default()
+        })
+        .add_plugins((
+            HeadlessPlugin,
+            headless_siege()
+            .disable::<FortressPlugin>()
+            .disable::<RadarPlugin>()
+            .disable::<TurretPlugin>()
+            .disable::<DefensePlugin>()
+            .disable::<AttackerAiPlugin>()
+        ))
+        .init_resource::<CalibrationGrid>()
+        .add_systems(Startup, startup)
+        .add_systems(Update, deploy
+            .run_if(resource_exists::<DefaultTrebuchetConfig>)
+            .run_if(not(resource_exists::<Calibration>))
+        )
+        .add_systems(FixedUpdate, record.run_if(resource_exists::<Calibration>).run_if(in_state(GameState::Siege)))
+        .add_systems(OnEnter(GameState::Results), finish)
+        ;
+    }
+}
+
+// ---
+
+#[derive(Resource)]
+pub struct CalibrationGrid {
+    pub cw_densities: Vec<f32>,
+    pub sling_lens: Vec<f32>,
+    pub unhooking_dots: Vec<f32>,
+    /// Distance between neighbouring trebuchets on the test line.
+    pub spacing: f32,
+    pub csv_path: String,
+    pub table_path: String,
+}
+
+impl Default for CalibrationGrid {
+    fn default() -> Self {
+        Self {
+            cw_densities: vec![7.5, 9.5, 11.5],
+            sling_lens: vec![9., 11.25, 13.5],
+            unhooking_dots: vec![0.9, 0.93, 0.96, 0.99],
+            spacing: 30.,
+            csv_path: "calibration.csv".into(),
+            table_path: "assets/configs/trebuchet.range.ron".into(),
+        }
+    }
+}
+
+struct Sample {
+    config: TrebuchetConfig,
+    /// Each sample's trebuchet gets its own config asset, which tells its balls apart.
+    config_id: AssetId<TrebuchetConfig>,
+    transform: Transform,
+    landing: Option<Vec3>,
+}
+
+#[derive(Resource)]
+struct Calibration {
+    samples: Vec<Sample>,
+}
+
+#[derive(Component)]
+struct CalibrationTMP;
+
+const LANDING_MARGIN: f32 = 0.1;
+
+// ---
+
+fn startup(
+    mut cmd: Commands
+) {
+    cmd.spawn((NotReady, CalibrationTMP));
+}
+
+// ---
+
+fn deploy(
+    mut cmd: Commands,
+    grid: Res<CalibrationGrid>,
+    default_config: Res<DefaultTrebuchetConfig>,
+    mut configs: ResMut<Assets<TrebuchetConfig>>,
+    assets: Res<AssetServer>,
+    tmp_q: Single<Entity, With<CalibrationTMP>>,
+) {
+    // grid values are laid over the default config file, so wait for it
+    let base = match configs.get(&default_config.0) {
+        Some(c) => c.clone(),
+        None if assets.load_state(&default_config.0).is_failed() => TrebuchetConfig::default(),
+        None => return,
+    };
+
+    let mut samples = Vec::new();
+    for &cw_density in &grid.cw_densities {
+        for &sling_len in &grid.sling_lens {
+            for &unhooking_dot in &grid.unhooking_dots {
+                let config = TrebuchetConfig { cw_density, sling_len, unhooking_dot, ..base.clone() };
+                let transform = Transform::from_xyz(samples.len() as f32 * grid.spacing, 0.1, 40.);
+                let handle = configs.add(config.clone());
+                let config_id = handle.id();
+                cmd.trigger(TrebuchetSpawn {
+                    transform,
+                    config: Some(handle),
+                    first_throw: None,
+                });
+                samples.push(Sample { config, config_id, transform, landing: None });
+            }
+        }
+    }
+    info!("Calibrating {} trebuchets", samples.len());
+    cmd.insert_resource(Calibration { samples });
+    cmd.entity(tmp_q.into_inner()).despawn();
+}
+
+// ---
+
+fn record(
+    ball_q: Query<(&Transform, &LinearVelocity, &ProjectleOwner, &ProjectleKey), (With<Ball>, With<Released>)>,
+    treb_q: Query<&TrebuchetConfigHandle, With<Trebuchet>>,
+    mut calibration: ResMut<Calibration>,
+    mut exit: EventWriter<AppExit>,
+    grid: Res<CalibrationGrid>,
+    registry: Res<ProjectleRegistry>,
+) {
+    for (ball_t, vel, owner, key) in &ball_q {
+        let radius = registry.get(key).map_or(BALL_RADIUS, |def| def.radius);
+        if vel.0.y > 0. || ball_t.translation.y > radius + LANDING_MARGIN {
+            continue;
+        }
+        let Ok(config_handle) = treb_q.get(owner.0) else {
+            continue;
+        };
+        let Some(sample) = calibration.samples.iter_mut().find(|s| {
+            s.landing.is_none() && s.config_id == config_handle.0.id()
+        }) else {
+            continue;
+        };
+        sample.landing = Some(ball_t.translation);
+    }
+
+    if calibration.samples.iter().all(|s| s.landing.is_some()) {
+        write(&calibration, &grid);
+        exit.send(AppExit::Success);
+    }
+}
+
+// ---
+
+fn finish(
+    calibration: Option<Res<Calibration>>,
+    grid: Res<CalibrationGrid>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(calibration) = calibration {
+        write(&calibration, &grid);
+    }
+    exit.send(AppExit::Success);
+}
+
+// ---
+
+fn write(calibration: &Calibration, grid: &CalibrationGrid) {
+    let mut csv = String::from("cw_density,sling_len,unhooking_dot,range,drift\n");
+    let mut table = RangeTable { entries: Vec::new() };
+    for s in &calibration.samples {
+        let Some(landing) = s.landing else {
+            warn!("No landing for cw {} sling {} dot {}", s.config.cw_density, s.config.sling_len, s.config.unhooking_dot);
+            continue;
+        };
+        let offset = landing - s.transform.translation;
+        let entry = RangeEntry {
+            cw_density: s.config.cw_density,
+            sling_len: s.config.sling_len,
+            unhooking_dot: s.config.unhooking_dot,
+            range: offset.dot(*s.transform.forward()),
+            drift: offset.dot(*s.transform.right()),
+        };
+        let _ = writeln!(csv, "{},{},{},{:.2},{:.2}", entry.cw_density, entry.sling_len, entry.unhooking_dot, entry.range, entry.drift);
+        table.entries.push(entry);
+    }
+
+    if let Err(e) = std::fs::write(&grid.csv_path, csv) {
+        error!("Could not write {}: {e}", grid.csv_path);
+    }
+    match ron::ser::to_string_pretty(&table, ron::ser::PrettyConfig::default()) {
+        Ok(ron) => if let Err(e) = std::fs::write(&grid.table_path, ron) {
+            error!("Could not write {}: {e}", grid.table_path);
+        },
+        Err(e) => error!("Could not serialize range table: {e}"),
+    }
+    info!("Range table with {} entries written to {}", table.entries.len(), grid.table_path);
+}
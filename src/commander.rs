@@ -0,0 +1,190 @@
+use avian3d::prelude::*;
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::camera::Cam;
+use crate::shared::{Ammo, GameLayer, Interval};
+use crate::trebuchet::{
+    ArmNow, Commanded, HoldFire, ReleaseNow, StateArming, StateIdle, StateLoose, StateTension, Trebuchet,
+    TrebuchetTarget
+};
+use crate::GameState;
+
+// ---
+
+/// Lets the player run a trebuchet by hand. Picking one puts it under command: it stops
+/// throwing on its own, pulls, loads and lets the ball go only when told. Picking another
+/// hands the previous one back to its crew.
+///
+/// - Left click, Tab / Shift+Tab: select a trebuchet
+/// - Right click: turn the selected trebuchet toward a point on the ground
+/// - R: pull the arm down
+/// - A: load the ball, once the arm is down
+/// - Space: release
+/// - X: hand the selected trebuchet back to the automatic crew
+pub struct CommanderPlugin;
+impl Plugin for CommanderPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, (select, command).chain().run_if(in_state(GameState::Siege)))
+        .add_systems(Update, highlight.run_if(any_with_component::<Commanded>).run_if(not(in_state(GameState::Loading))))
+        ;
+    }
+}
+
+// ---
+
+/// The trebuchet the commander's orders go to.
+#[derive(Component)]
+pub struct Selected;
+
+/// How close to a trebuchet a click has to land to pick it.
+const PICK_RADIUS: f32 = 12.;
+
+// ---
+
+/// Where the cursor points on the field.
+fn cursor_ground(
+    window: &Window,
+    cam: (&Camera, &GlobalTransform),
+    spatial: &SpatialQuery,
+) -> Option<Vec3> {
+    let ray = cam.0.viewport_to_world(cam.1, window.cursor_position()?).ok()?;
+    let filter = SpatialQueryFilter::from_mask(GameLayer::Env);
+    if let Some(hit) = spatial.cast_ray(ray.origin, ray.direction, 1000., true, &filter) {
+        return Some(ray.get_point(hit.distance));
+    }
+    ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y)).map(|d| ray.get_point(d))
+}
+
+// ---
+
+fn select(
+    treb_q: Query<(Entity, &Transform), With<Trebuchet>>,
+    selected_q: Query<(Entity, Has<StateIdle>), With<Selected>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Cam>>,
+    spatial: SpatialQuery,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cmd: Commands,
+) {
+    let current = selected_q.iter().next();
+    let pick = if mouse.just_pressed(MouseButton::Left) {
+        let Some(ground) = cursor_ground(&window, cam.into_inner(), &spatial) else {
+            return;
+        };
+        treb_q.iter()
+            .map(|(e, t)| (e, t.translation.with_y(0.).distance(ground.with_y(0.))))
+            .filter(|(_, d)| *d < PICK_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(e, _)| e)
+    } else if keys.just_pressed(KeyCode::Tab) {
+        // left to right along the field, wrapping around
+        let mut order: Vec<_> = treb_q.iter().collect();
+        order.sort_by(|a, b| a.1.translation.x.total_cmp(&b.1.translation.x).then(a.0.cmp(&b.0)));
+        let at = current.and_then(|(c, _)| order.iter().position(|(e, _)| *e == c));
+        let back = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+        let next = match (at, back) {
+            (None, _) => 0,
+            (Some(i), false) => (i + 1) % order.len().max(1),
+            (Some(i), true) => (i + order.len() - 1) % order.len().max(1),
+        };
+        order.get(next).map(|(e, _)| *e)
+    } else {
+        return;
+    };
+
+    let Some(pick) = pick else {
+        return;
+    };
+    if current.is_some_and(|(c, _)| c == pick) {
+        return;
+    }
+    if let Some((current, idle)) = current {
+        hand_back(&mut cmd, current, idle);
+    }
+    // the automatic crew's aim point would turn it away from where the player wants it,
+    // and a volley hold would keep it from pulling
+    cmd.entity(pick).insert((Selected, Commanded)).remove::<(TrebuchetTarget, HoldFire)>();
+}
+
+// ---
+
+/// Gives a trebuchet back to its crew, whatever it's doing: an idle one gets a fresh interval
+/// from `enter_idle`, one mid-throw carries on and lets go at its own release point.
+fn hand_back(cmd: &mut Commands, treb_e: Entity, idle: bool) {
+    cmd.entity(treb_e).remove::<(Selected, Commanded, ArmNow, ReleaseNow)>();
+    if idle {
+        cmd.entity(treb_e).remove::<StateIdle>().insert(StateIdle);
+    }
+}
+
+// ---
+
+fn command(
+    treb_q: Query<
+        (Entity, Has<StateIdle>, Has<StateTension>, Has<StateLoose>, Option<&Ammo>),
+        (With<Selected>, With<Commanded>)
+    >,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Cam>>,
+    spatial: SpatialQuery,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cmd: Commands,
+) {
+    let Ok((treb_e, idle, tension, loose, ammo)) = treb_q.get_single() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::KeyR) && idle && ammo.is_none_or(|a| a.0 != 0) {
+        cmd.entity(treb_e)
+        .remove::<(StateIdle, Interval)>()
+        .insert(StateTension)
+        ;
+    }
+    if keys.just_pressed(KeyCode::KeyA) && tension {
+        cmd.entity(treb_e).insert(ArmNow);
+    }
+    if keys.just_pressed(KeyCode::Space) && loose {
+        cmd.entity(treb_e).insert(ReleaseNow);
+    }
+    if mouse.just_pressed(MouseButton::Right) {
+        if let Some(ground) = cursor_ground(&window, cam.into_inner(), &spatial) {
+            cmd.entity(treb_e).insert(TrebuchetTarget(ground));
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyX) {
+        hand_back(&mut cmd, treb_e, idle);
+    }
+}
+
+// ---
+
+fn highlight(
+    treb_q: Query<
+        (&Transform, Has<Selected>, Has<StateTension>, Has<StateArming>, Has<StateLoose>, Option<&TrebuchetTarget>),
+        With<Commanded>
+    >,
+    mut gizmos: Gizmos,
+) {
+    for (t, selected, tension, arming, loose, target) in &treb_q {
+        let color = if loose {
+            Color::srgb(1., 0.2, 0.2)
+        } else if tension || arming {
+            Color::srgb(1., 0.7, 0.1)
+        } else {
+            Color::srgb(0.2, 1., 0.4)
+        };
+        let ground = Isometry3d::new(t.translation.with_y(0.3), Quat::from_rotation_x(std::f32::consts::FRAC_PI_2));
+        gizmos.circle(ground, PICK_RADIUS * 0.8, color.with_alpha(0.4));
+        if !selected {
+            continue;
+        }
+        gizmos.circle(ground, PICK_RADIUS, color);
+        gizmos.arrow(t.translation + Vec3::Y, t.translation + Vec3::Y + t.forward() * PICK_RADIUS * 1.5, color);
+        if let Some(target) = target {
+            gizmos.line(t.translation.with_y(0.3), target.0.with_y(0.3), color.with_alpha(0.3));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::field::FortressPosition;
+use crate::fire_control::{Doctrine, DoctrineOverride};
+use crate::radar::{Radar, RadarSensor};
+use crate::ron_asset::RonAssetPlugin;
+use crate::turret::{FedBy, Turret, TurretSpec};
+use crate::NotReady;
+
+// ---
+
+/// Places radars and turrets around the fortress from a `*.defense.ron` layout.
+pub struct DefensePlugin;
+impl Plugin for DefensePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins(RonAssetPlugin::<DefenseLayout>::new(&["defense.ron"]))
+        .add_systems(Startup, startup)
+        .add_systems(Update, deploy
+            .run_if(resource_exists::<FortressPosition>)
+            .run_if(any_with_component::<DefenseTMP>)
+        )
+        ;
+    }
+}
+
+// ---
+
+/// Radar and turret types by name, and where each unit stands relative to the fortress.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct DefenseLayout {
+    #[serde(default)]
+    pub radar_types: HashMap<String, RadarSensor>,
+    #[serde(default)]
+    pub turret_types: HashMap<String, TurretSpec>,
+    /// Engagement rules for every turret, each can override single fields.
+    #[serde(default)]
+    pub doctrine: Doctrine,
+    pub radars: Vec<RadarPlacement>,
+    pub turrets: Vec<TurretPlacement>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RadarPlacement {
+    /// Referenced by turrets' `fed_by`.
+    pub name: String,
+    /// Key in `radar_types`, the default sensor if missing.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// From the fortress position, on the ground.
+    pub offset: Vec3,
+    /// Degrees around the vertical axis.
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TurretPlacement {
+    /// Key in `turret_types`, the default turret if missing.
+    #[serde(default)]
+    pub kind: Option<String>,
+    pub offset: Vec3,
+    #[serde(default)]
+    pub yaw: f32,
+    /// Radars whose tracks this turret engages; all of them when empty.
+    #[serde(default)]
+    pub fed_by: Vec<String>,
+    /// Laid over the layout's `doctrine`.
+    #[serde(default)]
+    pub doctrine: DoctrineOverride,
+}
+
+impl Default for DefenseLayout {
+    fn default() -> Self {
+        // two radars flanking the fortress, each with a turret 20 m inward
+        let radar = |name: &str, x: f32| RadarPlacement {
+            name: name.into(),
+            kind: None,
+            offset: Vec3::new(x, 0., 100.),
+            yaw: 180.,
+        };
+        let turret = |x: f32| TurretPlacement {
+            kind: None,
+            offset: Vec3::new(x, 0., 100.),
+            yaw: 180.,
+            fed_by: Vec::new(),
+            doctrine: DoctrineOverride::default(),
+        };
+        Self {
+            radar_types: HashMap::new(),
+            turret_types: HashMap::new(),
+            doctrine: Doctrine::default(),
+            radars: vec![radar("west", -100.), radar("east", 100.)],
+            turrets: vec![turret(-80.), turret(80.)],
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct DefenseLayoutHandle(pub Handle<DefenseLayout>);
+
+pub const DEFENSE_LAYOUT_PATH: &str = "configs/default.defense.ron";
+
+#[derive(Component)]
+struct DefenseTMP;
+
+// ---
+
+fn startup(
+    mut cmd: Commands,
+    assets: Res<AssetServer>,
+) {
+    cmd.insert_resource(DefenseLayoutHandle(assets.load(DEFENSE_LAYOUT_PATH)));
+    cmd.spawn((NotReady, DefenseTMP));
+}
+
+// ---
+
+fn deploy(
+    mut cmd: Commands,
+    handle: Res<DefenseLayoutHandle>,
+    layouts: Res<Assets<DefenseLayout>>,
+    assets: Res<AssetServer>,
+    ftp: Res<FortressPosition>,
+    tmp_q: Single<Entity, With<DefenseTMP>>,
+) {
+    let layout = match layouts.get(&handle.0) {
+        Some(l) => l.clone(),
+        None if assets.load_state(&handle.0).is_failed() => {
+            warn!("No defense layout at {DEFENSE_LAYOUT_PATH}, using the built-in one");
+            DefenseLayout::default()
+        },
+        None => return,
+    };
+
+    let place = |offset: Vec3, yaw: f32| {
+        Transform::from_translation(ftp.0.with_y(0.) + offset).with_rotation(Quat::from_rotation_y(yaw.to_radians()))
+    };
+
+    let mut radars = HashMap::new();
+    for r in &layout.radars {
+        let sensor = r.kind.as_ref()
+            .and_then(|k| lookup(&layout.radar_types, k, "radar"))
+            .copied()
+            .unwrap_or_default();
+        let radar_e = cmd.spawn((
+            place(r.offset, r.yaw),
+            Radar,
+            sensor,
+            Name::new("Radar"),
+        ))
+        .id();
+        radars.insert(r.name.clone(), radar_e);
+    }
+
+    for t in &layout.turrets {
+        let spec = t.kind.as_ref()
+            .and_then(|k| lookup(&layout.turret_types, k, "turret"))
+            .cloned()
+            .unwrap_or_default();
+        let fed_by = t.fed_by.iter()
+            .filter_map(|name| {
+                let radar = radars.get(name).copied();
+                if radar.is_none() {
+                    warn!("Turret fed by unknown radar {name}");
+                }
+                radar
+            })
+            .collect();
+        cmd.spawn((
+            place(t.offset, t.yaw),
+            Turret,
+            spec.ammo,
+            spec,
+            FedBy(fed_by),
+            layout.doctrine.overridden(&t.doctrine),
+        ));
+    }
+
+    info!("Defense deployed: {} radars, {} turrets", layout.radars.len(), layout.turrets.len());
+    cmd.entity(tmp_q.into_inner()).despawn();
+}
+
+fn lookup<'a, T>(types: &'a HashMap<String, T>, kind: &str, what: &str) -> Option<&'a T> {
+    let found = types.get(kind);
+    if found.is_none() {
+        warn!("Unknown {what} type {kind}, using the default");
+    }
+    found
+}
@@ -1,12 +1,13 @@
 use avian3d::prelude::*;
 use bevy::{prelude::*, scene::SceneInstanceReady};
 
-use crate::shared::GameLayer;
+use crate::shared::{GameLayer, Headless};
 pub struct FieldPlugin;
 impl Plugin for FieldPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(Startup, startup)
+        .add_systems(Startup, startup.run_if(not(resource_exists::<Headless>)))
+        .add_systems(Startup, stand_in.run_if(resource_exists::<Headless>))
         ;
     }
 }
@@ -33,6 +34,23 @@ fn startup(
 
 // ---
 
+const STAND_IN_FORTRESS_POSITION: Vec3 = Vec3::new(0., 0., -120.);
+
+fn stand_in(
+    mut cmd: Commands,
+) {
+    cmd.spawn((
+        RigidBody::Static,
+        Collider::half_space(Vec3::Y),
+        CollisionLayers::new([GameLayer::Env], [LayerMask::ALL]),
+        Transform::IDENTITY,
+        Name::new("Field"),
+    ));
+    cmd.insert_resource(FortressPosition(STAND_IN_FORTRESS_POSITION));
+}
+
+// ---
+
 fn setup(
     tr: Trigger<SceneInstanceReady>,
     mut cmd: Commands,
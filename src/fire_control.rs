@@ -0,0 +1,288 @@
+use std::collections::{HashMap, HashSet};
+
+use avian3d::prelude::Gravity;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::MatchClock;
+use crate::radar::{RadarSet, RadarTracks, Track};
+use crate::turret::{can_engage, Barrel, BarrelTurret, FedBy, Fire, Manned, Target, Turret};
+use crate::turret_supply::{AmmoStore, Magazine};
+use crate::GameState;
+
+// ---
+
+/// Hands barrels to radar tracks, most threatening first. A barrel keeps its track until the
+/// track is lost, unless a much bigger threat has nobody to engage it. Turrets fed by certain
+/// radars only take tracks those radars have seen, and a turret's `Doctrine` decides which of
+/// those are worth its rounds.
+pub struct FireControlPlugin;
+impl Plugin for FireControlPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(FixedUpdate, assign.after(RadarSet).run_if(in_state(GameState::Siege)))
+        ;
+    }
+}
+
+// ---
+
+/// Engagement rules for a turret. Barrels drop tracks that stop meeting them.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Doctrine {
+    /// Bearings the turret answers for, everywhere if unset.
+    pub sector: Option<Sector>,
+    /// Farthest track from the turret it engages.
+    pub max_range: f32,
+    /// Tracks landing sooner than this, in seconds, are left alone: there's no time to bring
+    /// them down.
+    pub min_time_to_impact: f32,
+    /// Tracks predicted to do less damage than this aren't worth a burst.
+    pub min_damage: f32,
+    /// Rounds left (store and magazines) at or below which the turret saves itself for big threats.
+    pub reserve: u32,
+    /// Threat a track needs once the turret is down to its reserve.
+    pub reserve_threat: f32,
+}
+
+impl Default for Doctrine {
+    fn default() -> Self {
+        Self {
+            sector: None,
+            max_range: 250.,
+            min_time_to_impact: 0.,
+            min_damage: 0.,
+            reserve: 0,
+            reserve_threat: 0.,
+        }
+    }
+}
+
+/// Per-turret changes to a layout-wide `Doctrine`: set fields replace the shared value,
+/// the rest are kept.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DoctrineOverride {
+    pub sector: Option<Sector>,
+    pub max_range: Option<f32>,
+    pub min_time_to_impact: Option<f32>,
+    pub min_damage: Option<f32>,
+    pub reserve: Option<u32>,
+    pub reserve_threat: Option<f32>,
+}
+
+/// A slice of the horizon around the turret, in degrees from its facing, positive to its left.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Sector {
+    pub center: f32,
+    pub width: f32,
+}
+
+impl Sector {
+    fn contains(&self, facing: Vec3, offset: Vec3) -> bool {
+        let f = facing.with_y(0.).normalize_or_zero();
+        let d = offset.with_y(0.).normalize_or_zero();
+        let bearing = f.cross(d).y.atan2(f.dot(d)).to_degrees();
+        let delta = (bearing - self.center + 180.).rem_euclid(360.) - 180.;
+        delta.abs() <= self.width * 0.5
+    }
+}
+
+impl Doctrine {
+    /// This doctrine with the fields `over` sets replaced.
+    pub fn overridden(&self, over: &DoctrineOverride) -> Self {
+        Self {
+            sector: over.sector.or(self.sector),
+            max_range: over.max_range.unwrap_or(self.max_range),
+            min_time_to_impact: over.min_time_to_impact.unwrap_or(self.min_time_to_impact),
+            min_damage: over.min_damage.unwrap_or(self.min_damage),
+            reserve: over.reserve.unwrap_or(self.reserve),
+            reserve_threat: over.reserve_threat.unwrap_or(self.reserve_threat),
+        }
+    }
+
+    /// Whether a turret at `turret` with `rounds` left may take `track`, now estimated at `pos`.
+    pub fn permits(&self, turret: &GlobalTransform, rounds: u32, track: &Track, pos: Vec3) -> bool {
+        let offset = pos - turret.translation();
+        if offset.length() > self.max_range {
+            return false;
+        }
+        if self.sector.is_some_and(|s| !s.contains(*turret.forward(), offset)) {
+            return false;
+        }
+        if track.time_to_impact.is_some_and(|t| t < self.min_time_to_impact) || track.predicted_damage < self.min_damage {
+            return false;
+        }
+        rounds > self.reserve || track.threat >= self.reserve_threat
+    }
+}
+
+/// How many times more threatening an unengaged track has to be to take a busy barrel.
+const REASSIGN_RATIO: f32 = 1.5;
+
+// ---
+
+fn assign(
+    tracks: Option<Res<RadarTracks>>,
+    barrel_q: Query<(Entity, &GlobalTransform, Option<&Target>, &BarrelTurret, &Magazine), (With<Barrel>, Without<Manned>)>,
+    turret_q: Query<(&GlobalTransform, Option<&FedBy>, Option<&Doctrine>, Option<&AmmoStore>), With<Turret>>,
+    gravity: Res<Gravity>,
+    clock: Res<MatchClock>,
+    mut cmd: Commands,
+) {
+    let Some(tracks) = tracks else {
+        return;
+    };
+
+    // turret -> rounds left in its store and magazines
+    let mut rounds: HashMap<Entity, u32> = HashMap::new();
+    for (_, _, _, barrel_turret, magazine) in &barrel_q {
+        *rounds.entry(barrel_turret.0).or_insert_with(|| {
+            turret_q.get(barrel_turret.0).ok().and_then(|(.., store)| store).map_or(0, |s| s.rounds)
+        }) += magazine.rounds;
+    }
+
+    let permits = |barrel_e: Entity, track: &Track, pos: Vec3| {
+        let Ok((_, gt, _, barrel_turret, _)) = barrel_q.get(barrel_e) else {
+            return false;
+        };
+        let Ok((turret_gt, fed_by, doctrine, _)) = turret_q.get(barrel_turret.0) else {
+            return false;
+        };
+        if !can_engage(*turret_gt.forward(), gt.translation(), pos) {
+            return false;
+        }
+        let fed = fed_by.is_none_or(|f| f.0.is_empty() || f.0.iter().any(|r| track.seen_by.contains(r)));
+        fed && doctrine.is_none_or(|d| d.permits(turret_gt, rounds.get(&barrel_turret.0).copied().unwrap_or(0), track, pos))
+    };
+
+    // barrel -> threat of the track it's on
+    let mut busy: HashMap<Entity, f32> = HashMap::new();
+    let mut engaged = HashSet::new();
+    let mut free = Vec::new();
+    for (barrel_e, _, target, _, _) in &barrel_q {
+        let kept = target.and_then(|t| tracks.get(t.0)).filter(|track| {
+            track.position_at(clock.elapsed, gravity.0).is_some_and(|pos| permits(barrel_e, track, pos))
+        });
+        match kept {
+            Some(track) => {
+                busy.insert(barrel_e, track.threat);
+                engaged.insert(track.entity);
+            },
+            None => {
+                if target.is_some() {
+                    cmd.entity(barrel_e).remove::<Target>();
+                }
+                free.push(barrel_e);
+            },
+        }
+    }
+
+    for track in &tracks.0 {
+        if engaged.contains(&track.entity) {
+            continue;
+        }
+        let Some(pos) = track.position_at(clock.elapsed, gravity.0) else {
+            continue;
+        };
+        let reach = |e: &Entity| permits(*e, track, pos);
+        let distance = |e: &Entity| barrel_q.get(*e).map_or(f32::MAX, |(_, gt, ..)| gt.translation().distance_squared(pos));
+
+        let nearest_free = free.iter()
+            .filter(|e| reach(e))
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .copied();
+        let barrel_e = match nearest_free {
+            Some(e) => {
+                free.retain(|f| *f != e);
+                e
+            },
+            None => {
+                let Some((&e, _)) = busy.iter()
+                    .filter(|(e, threat)| reach(e) && track.threat > **threat * REASSIGN_RATIO)
+                    .min_by(|a, b| a.1.total_cmp(b.1).then(a.0.cmp(b.0)))
+                else {
+                    continue;
+                };
+                cmd.entity(e).remove::<Fire>();
+                e
+            },
+        };
+        busy.insert(barrel_e, track.threat);
+        engaged.insert(track.entity);
+        cmd.entity(barrel_e).insert(Target(track.entity));
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    fn track(time_to_impact: f32, predicted_damage: f32, threat: f32) -> Track {
+        Track {
+            entity: Entity::PLACEHOLDER,
+            history: VecDeque::new(),
+            velocity: Vec3::ZERO,
+            impact: None,
+            time_to_impact: Some(time_to_impact),
+            predicted_damage,
+            threat,
+            seen_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sector_bearings_are_positive_to_the_left() {
+        let left = Sector { center: 90., width: 60. };
+        assert!(left.contains(Vec3::NEG_Z, Vec3::new(-10., 0., 0.)));
+        assert!(!left.contains(Vec3::NEG_Z, Vec3::new(10., 0., 0.)));
+        assert!(!left.contains(Vec3::NEG_Z, Vec3::new(0., 0., -10.)));
+    }
+
+    #[test]
+    fn sector_wraps_around_behind() {
+        let behind = Sector { center: 180., width: 40. };
+        assert!(behind.contains(Vec3::NEG_Z, Vec3::new(1., 0., 10.)));
+        assert!(behind.contains(Vec3::NEG_Z, Vec3::new(-1., 0., 10.)));
+        assert!(!behind.contains(Vec3::NEG_Z, Vec3::new(10., 0., 1.)));
+    }
+
+    #[test]
+    fn override_replaces_only_set_fields() {
+        let shared = Doctrine { min_damage: 50., reserve_threat: 100., ..default() };
+        let own = shared.overridden(&DoctrineOverride { max_range: Some(120.), reserve: Some(5), ..default() });
+        assert_eq!(own.max_range, 120.);
+        assert_eq!(own.reserve, 5);
+        assert_eq!(own.min_damage, 50.);
+        assert_eq!(own.reserve_threat, 100.);
+        assert!(own.sector.is_none());
+    }
+
+    #[test]
+    fn permits_checks_range_timing_and_damage() {
+        let turret = GlobalTransform::IDENTITY;
+        let pos = Vec3::new(0., 20., -100.);
+        let t = track(5., 100., 10.);
+        assert!(Doctrine::default().permits(&turret, 100, &t, pos));
+        assert!(!Doctrine { max_range: 50., ..default() }.permits(&turret, 100, &t, pos));
+        assert!(!Doctrine { min_time_to_impact: 10., ..default() }.permits(&turret, 100, &t, pos));
+        assert!(!Doctrine { min_damage: 200., ..default() }.permits(&turret, 100, &t, pos));
+        let behind = Sector { center: 180., width: 90. };
+        assert!(!Doctrine { sector: Some(behind), ..default() }.permits(&turret, 100, &t, pos));
+    }
+
+    #[test]
+    fn reserve_is_kept_for_big_threats() {
+        let turret = GlobalTransform::IDENTITY;
+        let pos = Vec3::new(0., 20., -100.);
+        let doctrine = Doctrine { reserve: 50, reserve_threat: 20., ..default() };
+        assert!(!doctrine.permits(&turret, 40, &track(5., 100., 10.), pos));
+        assert!(doctrine.permits(&turret, 40, &track(5., 100., 30.), pos));
+        assert!(doctrine.permits(&turret, 60, &track(5., 100., 10.), pos));
+    }
+}
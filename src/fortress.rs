@@ -1,16 +1,28 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
 use bevy::{
     prelude::*,
-    scene::SceneInstanceReady
+    scene::SceneInstanceReady,
+    utils::{HashMap, HashSet}
 };
 use avian3d:: prelude::*;
-use crate::{field::FortressPosition, NotReady};
+use crate::{field::FortressPosition, projectle::Ball, shared::Headless, GameState, NotReady};
 
 
 pub struct FortressPlugin;
 impl Plugin for FortressPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(Update, startup.run_if(resource_added::<FortressPosition>))
+        .add_systems(Update, startup.run_if(resource_added::<FortressPosition>).run_if(not(resource_exists::<Headless>)))
+        .add_systems(Update, stand_in.run_if(resource_added::<FortressPosition>).run_if(resource_exists::<Headless>))
+        .add_systems(OnEnter(GameState::Countdown), (build_support_graph, measure_integrity))
+        .add_systems(FixedUpdate, (impact.run_if(on_event::<CollisionStarted>), track_strike).chain())
+        .register_required_components::<Ball, StrikeVelocity>()
+        .init_resource::<SupportGraph>()
+        .init_resource::<FortressIntegrity>()
+        .add_observer(take_damage)
+        .add_observer(break_loose)
+        .add_observer(collapse)
         ;
     }
 }
@@ -24,6 +36,73 @@ pub struct FortressTMP;
 #[derive(Component)]
 pub struct Fortress;
 
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32
+}
+
+impl Health {
+    fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+#[derive(Component)]
+pub struct PieceMass(pub f32);
+
+/// A piece knocked out of the masonry, simulated as a dynamic body from now on.
+#[derive(Component)]
+pub struct Loose;
+
+/// A ball's velocity going into the physics step. By the time `CollisionStarted` is read the
+/// solver has already stopped the ball, so this is what it struck with.
+#[derive(Component, Default)]
+pub struct StrikeVelocity(pub Vec3);
+
+/// Damage dealt to a fortress piece, triggered on the piece entity.
+#[derive(Event)]
+pub struct Damage(pub f32);
+
+/// Which pieces rest on which, built from piece bounds once the fortress is loaded.
+#[derive(Resource, Default)]
+pub struct SupportGraph {
+    below: HashMap<Entity, Vec<Entity>>,
+    above: HashMap<Entity, Vec<Entity>>,
+    grounded: HashSet<Entity>,
+}
+
+#[derive(Resource, Default)]
+pub struct FortressIntegrity {
+    pub total_mass: f32,
+    pub loose_mass: f32,
+}
+
+impl FortressIntegrity {
+    /// Share of the fortress mass knocked loose, from 0 to 1.
+    pub fn destroyed(&self) -> f32 {
+        if self.total_mass > 0. {
+            self.loose_mass / self.total_mass
+        } else {
+            0.
+        }
+    }
+}
+
+// ---
+
+const PIECE_DENSITY: f32 = 0.1;
+const BRICK_HEALTH: f32 = 300.;
+const PILLAR_HEALTH: f32 = 800.;
+const ROOF_HEALTH: f32 = 600.;
+const DISK_HEALTH: f32 = 1000.;
+/// Damage per unit of momentum (mass times closing speed) a ball strikes a piece with.
+const IMPACT_DAMAGE: f32 = 1.;
+/// Max vertical gap between two touching pieces' bounds for one to carry the other.
+const SUPPORT_TOLERANCE: f32 = 0.5;
+
+// ---
+
 fn startup(
     mut cmd: Commands,
     assets: ResMut<AssetServer>,
@@ -42,6 +121,46 @@ fn startup(
 
 // ---
 
+fn stand_in(
+    mut cmd: Commands,
+    fp: Res<FortressPosition>
+) {
+    // four towers joined by brick walls, roughly the footprint of models/fortress.glb
+    let half = 20.;
+    let fortress_e = cmd.spawn((
+        Transform::from_translation(fp.0),
+        Fortress,
+        Name::new("Fortress"),
+    )).id();
+
+    let mut pieces: Vec<(&str, Transform)> = Vec::new();
+    for (x, z) in [(-half, -half), (half, -half), (-half, half), (half, half)] {
+        pieces.push(("pillar", Transform::from_xyz(x, 4., z)));
+        pieces.push(("pillar", Transform::from_xyz(x, 12., z)));
+        pieces.push(("disk", Transform::from_xyz(x, 17., z)));
+        pieces.push(("roof", Transform::from_xyz(x, 25.5, z)));
+    }
+    for row in 0..3 {
+        let y = 2. + row as f32 * 4.;
+        for i in 0..4 {
+            let along = -12. + i as f32 * 8.;
+            for side in [-half, half] {
+                pieces.push(("brick", Transform::from_xyz(along, y, side)));
+                pieces.push(("brick", Transform::from_xyz(side, y, along).with_rotation(Quat::from_rotation_y(FRAC_PI_2))));
+            }
+        }
+    }
+
+    for (i, (kind, t)) in pieces.into_iter().enumerate() {
+        let name = format!("{kind}.{i:03}");
+        let piece_e = cmd.spawn((t, Name::new(name.clone()))).id();
+        fit(&mut cmd, piece_e, &name);
+        cmd.entity(fortress_e).add_child(piece_e);
+    }
+}
+
+// ---
+
 fn setup (
     tr: Trigger<SceneInstanceReady>,
     mut cmd: Commands,
@@ -52,25 +171,211 @@ fn setup (
 ) {
     for  c in children_q.iter_descendants_depth_first(tr.entity()) {
         if let Ok(name) = name_q.get(c) {
-            if name.starts_with("brick") || name.starts_with("disk") || name.starts_with("pillar") || name.starts_with("roof") {
-                cmd.entity(c).insert((
-                    // RigidBody::Dynamic,
-                    RigidBody::Static,
-                    ColliderDensity(0.1),
-                    Friction::new(0.1)
-                ));
-
-                if name.starts_with("brick") {
-                    cmd.entity(c).insert(Collider::cuboid(8., 4., 4.));
-                } else if name.starts_with("pillar") {
-                    cmd.entity(c).insert(Collider::cylinder(3., 8.));
-                } else if name.starts_with("roof") {
-                    cmd.entity(c).insert(Collider::cone(10., 15.));
-                } else if name.starts_with("disk") {
-                    cmd.entity(c).insert(Collider::cylinder(10., 2.));
-                } 
-            }
+            fit(&mut cmd, c, name);
         }
     }
     cmd.entity(ready_q.into_inner()).despawn();    
-}
\ No newline at end of file
+}
+
+// ---
+
+fn fit(cmd: &mut Commands, e: Entity, name: &str) {
+    if name.starts_with("brick") || name.starts_with("disk") || name.starts_with("pillar") || name.starts_with("roof") {
+        cmd.entity(e).insert((
+            // RigidBody::Dynamic,
+            RigidBody::Static,
+            ColliderDensity(PIECE_DENSITY),
+            Friction::new(0.1)
+        ));
+
+        if name.starts_with("brick") {
+            cmd.entity(e).insert((Collider::cuboid(8., 4., 4.), Health::new(BRICK_HEALTH), PieceMass(8. * 4. * 4. * PIECE_DENSITY)));
+        } else if name.starts_with("pillar") {
+            cmd.entity(e).insert((Collider::cylinder(3., 8.), Health::new(PILLAR_HEALTH), PieceMass(PI * 3. * 3. * 8. * PIECE_DENSITY)));
+        } else if name.starts_with("roof") {
+            cmd.entity(e).insert((Collider::cone(10., 15.), Health::new(ROOF_HEALTH), PieceMass(PI * 10. * 10. * 15. / 3. * PIECE_DENSITY)));
+        } else if name.starts_with("disk") {
+            cmd.entity(e).insert((Collider::cylinder(10., 2.), Health::new(DISK_HEALTH), PieceMass(PI * 10. * 10. * 2. * PIECE_DENSITY)));
+        } 
+    }
+}
+
+// ---
+
+fn impact(
+    mut collision_events: EventReader<CollisionStarted>,
+    collisions: Res<Collisions>,
+    ball_q: Query<(&StrikeVelocity, &ComputedMass, &Rotation), With<Ball>>,
+    piece_q: Query<(&Rotation, Option<&LinearVelocity>), With<Health>>,
+    mut cmd: Commands,
+) {
+    for CollisionStarted(e1, e2) in collision_events.read() {
+        let (ball_e, piece_e) = if ball_q.contains(*e1) && piece_q.contains(*e2) {
+            (*e1, *e2)
+        } else if ball_q.contains(*e2) && piece_q.contains(*e1) {
+            (*e2, *e1)
+        } else {
+            continue;
+        };
+        let (Ok((strike, mass, ball_rot)), Ok((piece_rot, piece_vel))) = (ball_q.get(ball_e), piece_q.get(piece_e)) else {
+            continue;
+        };
+        let Some(manifold) = collisions.get(*e1, *e2).and_then(|c| c.manifolds.first().map(|m| (c.entity1, m))) else {
+            continue;
+        };
+        // out of the ball, into the piece
+        let normal = match manifold {
+            (first, m) if first == ball_e => m.global_normal1(ball_rot),
+            (_, m) => -m.global_normal1(piece_rot),
+        };
+        let closing = (strike.0 - piece_vel.map_or(Vec3::ZERO, |v| v.0)).dot(normal).max(0.);
+        cmd.trigger_targets(Damage(mass.value() * closing * IMPACT_DAMAGE), piece_e);
+    }
+}
+
+// ---
+
+fn track_strike(
+    mut ball_q: Query<(&mut StrikeVelocity, &LinearVelocity)>,
+) {
+    for (mut strike, vel) in &mut ball_q {
+        strike.0 = vel.0;
+    }
+}
+
+// ---
+
+fn take_damage(
+    tr: Trigger<Damage>,
+    mut piece_q: Query<&mut Health, Without<Loose>>,
+    mut cmd: Commands,
+) {
+    let Ok(mut health) = piece_q.get_mut(tr.entity()) else {
+        return;
+    };
+    health.current -= tr.event().0;
+    if health.current <= 0. {
+        cmd.entity(tr.entity()).insert(Loose);
+    }
+}
+
+// ---
+
+fn break_loose(
+    tr: Trigger<OnAdd, Loose>,
+    mass_q: Query<&PieceMass>,
+    mut integrity: ResMut<FortressIntegrity>,
+    mut cmd: Commands,
+) {
+    cmd.entity(tr.entity()).insert(RigidBody::Dynamic);
+    if let Ok(mass) = mass_q.get(tr.entity()) {
+        integrity.loose_mass += mass.0;
+    }
+}
+
+// ---
+
+fn measure_integrity(
+    mass_q: Query<(&PieceMass, Has<Loose>)>,
+    mut integrity: ResMut<FortressIntegrity>,
+) {
+    integrity.total_mass = mass_q.iter().map(|(m, _)| m.0).sum();
+    integrity.loose_mass = mass_q.iter().filter(|(_, loose)| *loose).map(|(m, _)| m.0).sum();
+}
+
+// ---
+
+fn build_support_graph(
+    piece_q: Query<(Entity, &GlobalTransform, &Collider), With<Health>>,
+    mut graph: ResMut<SupportGraph>,
+) {
+    let bounds: Vec<(Entity, ColliderAabb)> = piece_q.iter().map(|(e, gt, collider)| {
+        let (scale, rotation, translation) = gt.to_scale_rotation_translation();
+        let mut collider = collider.clone();
+        collider.set_scale(scale, 8);
+        (e, collider.aabb(translation, rotation))
+    }).collect();
+
+    let Some(base_y) = bounds.iter().map(|(_, aabb)| aabb.min.y).min_by(f32::total_cmp) else {
+        return;
+    };
+
+    *graph = SupportGraph::default();
+    for (upper_e, upper) in &bounds {
+        if upper.min.y - base_y < SUPPORT_TOLERANCE {
+            graph.grounded.insert(*upper_e);
+            continue;
+        }
+        for (lower_e, lower) in &bounds {
+            if lower_e == upper_e || (upper.min.y - lower.max.y).abs() > SUPPORT_TOLERANCE {
+                continue;
+            }
+            let overlap_xz = lower.min.x < upper.max.x && upper.min.x < lower.max.x &&
+                lower.min.z < upper.max.z && upper.min.z < lower.max.z;
+            if overlap_xz {
+                graph.below.entry(*upper_e).or_default().push(*lower_e);
+                graph.above.entry(*lower_e).or_default().push(*upper_e);
+            }
+        }
+    }
+}
+
+// ---
+
+fn collapse(
+    tr: Trigger<OnAdd, Loose>,
+    mut graph: ResMut<SupportGraph>,
+    mut cmd: Commands,
+) {
+    let loose_e = tr.entity();
+    let Some(above) = graph.above.remove(&loose_e) else {
+        return;
+    };
+    for upper_e in above {
+        let grounded = graph.grounded.contains(&upper_e);
+        let Some(supporters) = graph.below.get_mut(&upper_e) else {
+            continue;
+        };
+        supporters.retain(|e| *e != loose_e);
+        if supporters.is_empty() && !grounded {
+            cmd.entity(upper_e).insert(Loose);
+        }
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::HeadlessPlugin;
+
+    #[test]
+    fn falling_ball_damages_a_brick() {
+        let mut app = App::new();
+        app.add_plugins((HeadlessPlugin, FortressPlugin));
+        app.finish();
+        app.cleanup();
+
+        let brick_e = app.world_mut().spawn((
+            Transform::default(),
+            RigidBody::Static,
+            Collider::cuboid(8., 4., 4.),
+            Health::new(BRICK_HEALTH),
+        )).id();
+        app.world_mut().spawn((
+            Ball,
+            Transform::from_xyz(0., 6., 0.),
+            RigidBody::Dynamic,
+            Collider::sphere(0.55),
+            ColliderDensity(14.5),
+            LinearVelocity(Vec3::NEG_Y * 20.),
+        ));
+        for _ in 0..64 {
+            app.update();
+        }
+
+        let health = app.world().get::<Health>(brick_e).unwrap();
+        assert!(health.current < health.max, "the ball did no damage");
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::{ecs::schedule::ExecutorKind, prelude::*};
+
+use crate::fortress::FortressIntegrity;
+use crate::projectle::Ball;
+use crate::rng::SiegeRng;
+use crate::shared::Ammo;
+use crate::trebuchet::Trebuchet;
+use crate::{GameState, NotReady};
+
+// ---
+
+/// Owns `GameState` and drives the match: Loading -> Countdown -> Siege <-> Paused,
+/// then Victory or Defeat (seen from the attacker's side) and finally Results.
+pub struct GamePlugin;
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_state::<GameState>()
+        .init_resource::<MatchRules>()
+        .init_resource::<MatchClock>()
+        .init_resource::<SiegeRng>()
+        // gameplay systems touching the same data would otherwise run in whatever order the
+        // threads pick, and the same seed would not replay the same siege
+        .edit_schedule(FixedUpdate, |s| { s.set_executor_kind(ExecutorKind::SingleThreaded); })
+        .add_systems(Startup, announce_seed)
+        // nothing steps until everything is loaded, however long the files take to read
+        .add_systems(OnEnter(GameState::Loading), pause)
+        .add_systems(OnExit(GameState::Loading), unpause)
+        .add_systems(Update, check_ready.run_if(in_state(GameState::Loading)))
+        .add_systems(OnEnter(GameState::Countdown), enter_countdown)
+        .add_systems(FixedUpdate, countdown.run_if(in_state(GameState::Countdown)))
+        .add_systems(FixedUpdate, judge.run_if(in_state(GameState::Siege)))
+        .add_systems(Update, toggle_pause
+            .run_if(resource_exists::<ButtonInput<KeyCode>>)
+            .run_if(in_state(GameState::Siege).or(in_state(GameState::Paused)))
+        )
+        .add_systems(OnEnter(GameState::Paused), pause)
+        .add_systems(OnExit(GameState::Paused), unpause)
+        .add_systems(OnEnter(GameState::Victory), enter_outcome)
+        .add_systems(OnEnter(GameState::Defeat), enter_outcome)
+        .add_systems(FixedUpdate, outcome.run_if(in_state(GameState::Victory).or(in_state(GameState::Defeat))))
+        .add_systems(OnEnter(GameState::Results), results)
+        ;
+    }
+}
+
+// ---
+
+#[derive(Resource)]
+pub struct MatchRules {
+    /// Seconds between loading and the first throw.
+    pub countdown: f32,
+    /// Siege seconds before the attackers give up.
+    pub time_limit: f32,
+    /// Share of fortress mass that has to be knocked loose to win.
+    pub victory_destroyed: f32,
+    /// Balls per trebuchet, `None` for unlimited.
+    pub ammo_per_trebuchet: Option<u32>,
+    /// Seconds the outcome is shown before Results.
+    pub results_delay: f32,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            countdown: 3.,
+            time_limit: 300.,
+            victory_destroyed: 0.5,
+            ammo_per_trebuchet: Some(20),
+            results_delay: 5.,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct MatchClock {
+    /// Seconds spent in `Siege`, pauses excluded.
+    pub elapsed: f32,
+    pub phase: Timer,
+}
+
+// ---
+
+fn announce_seed(
+    rng: Res<SiegeRng>
+) {
+    info!("Siege seed: {}", rng.seed());
+}
+
+// ---
+
+fn check_ready(
+    not_ready_q: Query<&NotReady>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    if not_ready_q.is_empty() {
+        info!("GAME!");
+        next.set(GameState::Countdown);
+    }
+}
+
+// ---
+
+fn enter_countdown(
+    mut clock: ResMut<MatchClock>,
+    rules: Res<MatchRules>,
+    treb_q: Query<Entity, With<Trebuchet>>,
+    mut cmd: Commands,
+) {
+    *clock = MatchClock {
+        elapsed: 0.,
+        phase: Timer::from_seconds(rules.countdown, TimerMode::Once),
+    };
+    if let Some(ammo) = rules.ammo_per_trebuchet {
+        for e in &treb_q {
+            cmd.entity(e).insert(Ammo(ammo));
+        }
+    }
+}
+
+// ---
+
+fn countdown(
+    mut clock: ResMut<MatchClock>,
+    mut next: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if clock.phase.tick(time.delta()).finished() {
+        next.set(GameState::Siege);
+    }
+}
+
+// ---
+
+fn judge(
+    mut clock: ResMut<MatchClock>,
+    rules: Res<MatchRules>,
+    integrity: Option<Res<FortressIntegrity>>,
+    ammo_q: Query<&Ammo, With<Trebuchet>>,
+    ball_q: Query<(), With<Ball>>,
+    mut next: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    clock.elapsed += time.delta_secs();
+
+    // no fortress to win against when `FortressPlugin` is left out, as in calibration
+    let destroyed = integrity.map_or(0., |i| i.destroyed());
+    if destroyed >= rules.victory_destroyed {
+        info!("Fortress destroyed: {:.0}%", destroyed * 100.);
+        next.set(GameState::Victory);
+    } else if clock.elapsed >= rules.time_limit {
+        info!("Time is up");
+        next.set(GameState::Defeat);
+    } else if !ammo_q.is_empty() && ammo_q.iter().all(|a| a.0 == 0) && ball_q.is_empty() {
+        info!("Out of ammo");
+        next.set(GameState::Defeat);
+    }
+}
+
+// ---
+
+fn toggle_pause(
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next.set(if *state.get() == GameState::Paused {GameState::Siege} else {GameState::Paused});
+    }
+}
+
+// ---
+
+fn pause(
+    mut time: ResMut<Time<Virtual>>
+) {
+    time.pause();
+}
+
+// ---
+
+fn unpause(
+    mut time: ResMut<Time<Virtual>>
+) {
+    time.unpause();
+}
+
+// ---
+
+fn enter_outcome(
+    mut clock: ResMut<MatchClock>,
+    rules: Res<MatchRules>,
+) {
+    clock.phase = Timer::from_seconds(rules.results_delay, TimerMode::Once);
+}
+
+// ---
+
+fn outcome(
+    mut clock: ResMut<MatchClock>,
+    mut next: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if clock.phase.tick(time.delta()).finished() {
+        next.set(GameState::Results);
+    }
+}
+
+// ---
+
+fn results(
+    clock: Res<MatchClock>,
+    integrity: Option<Res<FortressIntegrity>>,
+) {
+    let destroyed = integrity.map_or(0., |i| i.destroyed());
+    info!("Siege over after {:.1}s, fortress destroyed: {:.0}%", clock.elapsed, destroyed * 100.);
+}
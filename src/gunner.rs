@@ -0,0 +1,220 @@
+use avian3d::prelude::Gravity;
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use crate::ballistics::intercept;
+use crate::camera::Cam;
+use crate::game::MatchClock;
+use crate::projectle::ProjectleRegistry;
+use crate::radar::{RadarTracks, Track};
+use crate::turret::{muzzle_speed, Barrel, BarrelTurret, Fire, Manned, Target, Turret, MUZZLE_OFFSET};
+use crate::GameState;
+
+// ---
+
+/// Lets the player take a turret over from fire control: the camera moves behind it, the
+/// barrels follow the mouse and fire while the left button is held. Radar tracks are marked
+/// on screen with the lead point for the manned barrels.
+///
+/// - G: man the next turret
+/// - Left button: fire
+/// - X: hand the turret back to fire control
+pub struct GunnerPlugin;
+impl Plugin for GunnerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, (man, (aim, trigger, view).run_if(any_with_component::<Manned>)).chain().run_if(in_state(GameState::Siege)))
+        .add_systems(Update, markers.run_if(any_with_component::<Manned>).run_if(not(in_state(GameState::Loading))))
+        ;
+    }
+}
+
+// ---
+
+/// The turret the player is on.
+#[derive(Component)]
+pub struct GunnerSeat;
+
+/// How far out along the cursor ray the barrels converge when the cursor isn't on a lead marker.
+const AIM_DISTANCE: f32 = 300.;
+/// Angle (rad) between the cursor ray and a lead point within which the barrels converge
+/// at that lead point's distance.
+const SNAP_ANGLE: f32 = 0.05;
+/// Barrel turn rate toward the cursor, as in `turret::follow`.
+const AIM_SLERP: f32 = 50.;
+/// Camera offset behind and above the manned turret.
+const VIEW_BACK: f32 = 35.;
+const VIEW_UP: f32 = 18.;
+
+// ---
+
+fn man(
+    turret_q: Query<(Entity, &Transform), With<Turret>>,
+    seat_q: Query<Entity, With<GunnerSeat>>,
+    barrel_q: Query<(Entity, &BarrelTurret), With<Barrel>>,
+    mut pan_orbit: Single<&mut PanOrbitCamera, With<Cam>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cmd: Commands,
+) {
+    let current = seat_q.iter().next();
+    let next = if keys.just_pressed(KeyCode::KeyG) {
+        // left to right along the wall, wrapping around
+        let mut order: Vec<_> = turret_q.iter().collect();
+        order.sort_by(|a, b| a.1.translation.x.total_cmp(&b.1.translation.x).then(a.0.cmp(&b.0)));
+        let at = current.and_then(|c| order.iter().position(|(e, _)| *e == c));
+        order.get(at.map_or(0, |i| (i + 1) % order.len())).map(|(e, _)| *e)
+    } else if keys.just_pressed(KeyCode::KeyX) {
+        None
+    } else {
+        return;
+    };
+    if next.is_some() && next == current {
+        return;
+    }
+
+    if let Some(current) = current {
+        cmd.entity(current).remove::<GunnerSeat>();
+        for (barrel_e, _) in barrel_q.iter().filter(|(_, bt)| bt.0 == current) {
+            cmd.entity(barrel_e).remove::<(Manned, Fire)>();
+        }
+    }
+    if let Some(next) = next {
+        cmd.entity(next).insert(GunnerSeat);
+        for (barrel_e, _) in barrel_q.iter().filter(|(_, bt)| bt.0 == next) {
+            cmd.entity(barrel_e).remove::<(Target, Fire)>().insert(Manned);
+        }
+    }
+    // the orbit controls would fight the mouse aim, and put the view back once re-enabled
+    pan_orbit.enabled = next.is_none();
+}
+
+// ---
+
+fn aim(
+    mut barrel_q: Query<(&mut Transform, &GlobalTransform, &BarrelTurret), With<Manned>>,
+    turret_q: Query<&GlobalTransform, (With<Turret>, Without<Barrel>)>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Cam>>,
+    tracks: Res<RadarTracks>,
+    registry: Res<ProjectleRegistry>,
+    gravity: Res<Gravity>,
+    clock: Res<MatchClock>,
+    time: Res<Time>,
+) {
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let (camera, cam_gt) = cam.into_inner();
+    let Ok(ray) = camera.viewport_to_world(cam_gt, cursor) else {
+        return;
+    };
+    let speed = muzzle_speed(&registry);
+    for (mut barrel_t, barrel_gt, barrel_turret) in &mut barrel_q {
+        let Ok(turret_gt) = turret_q.get(barrel_turret.0) else {
+            continue;
+        };
+        // the camera sits behind the turret, so a point on the cursor ray is only where the
+        // barrel should go at the right depth: that of the lead marker under the cursor
+        let muzzle = barrel_gt.translation() + barrel_gt.forward() * MUZZLE_OFFSET;
+        let depth = tracks.0.iter()
+            .filter_map(|track| lead_point(track, muzzle, speed, gravity.0, clock.elapsed))
+            .map(|lead| {
+                let to_lead = lead - ray.origin;
+                (to_lead.dot(*ray.direction), to_lead.angle_between(*ray.direction))
+            })
+            .filter(|(d, angle)| *d > 0. && *angle < SNAP_ANGLE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(AIM_DISTANCE, |(d, _)| d);
+        let aim_pos = ray.get_point(depth);
+        let rotation_to_aim = barrel_gt.compute_transform().looking_at(aim_pos, Vec3::Y).rotation;
+        let turret_rotation = turret_gt.compute_transform().rotation;
+        barrel_t.rotation = barrel_t.rotation.slerp(
+            turret_rotation.inverse().mul_quat(rotation_to_aim),
+            (time.delta_secs() * AIM_SLERP).min(1.)
+        );
+    }
+}
+
+// ---
+
+fn trigger(
+    barrel_q: Query<Entity, With<Manned>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut cmd: Commands,
+) {
+    // `turret::fire` takes it from here: cooldown, magazine, heat and `ProjectleSpawn`
+    for barrel_e in &barrel_q {
+        if mouse.pressed(MouseButton::Left) {
+            cmd.entity(barrel_e).insert(Fire);
+        } else {
+            cmd.entity(barrel_e).remove::<Fire>();
+        }
+    }
+}
+
+// ---
+
+fn view(
+    seat_q: Query<&GlobalTransform, With<GunnerSeat>>,
+    mut cam_t: Single<&mut Transform, With<Cam>>,
+) {
+    let Ok(seat) = seat_q.get_single() else {
+        return;
+    };
+    let forward = seat.forward().with_y(0.).normalize_or(Vec3::Z);
+    let eye = seat.translation() - forward * VIEW_BACK + Vec3::Y * VIEW_UP;
+    **cam_t = Transform::from_translation(eye).looking_at(eye + forward * 100., Vec3::Y);
+}
+
+// ---
+
+fn markers(
+    tracks: Res<RadarTracks>,
+    barrel_q: Query<&GlobalTransform, With<Manned>>,
+    cam_gt: Single<&GlobalTransform, With<Cam>>,
+    registry: Res<ProjectleRegistry>,
+    gravity: Res<Gravity>,
+    clock: Res<MatchClock>,
+    mut gizmos: Gizmos,
+) {
+    let cam_pos = cam_gt.translation();
+    let facing = cam_gt.compute_transform().rotation;
+    let speed = muzzle_speed(&registry);
+
+    for barrel_gt in &barrel_q {
+        gizmos.ray(
+            barrel_gt.translation() + barrel_gt.forward() * MUZZLE_OFFSET,
+            barrel_gt.forward() * AIM_DISTANCE,
+            Color::srgba(1., 0.85, 0.3, 0.3)
+        );
+    }
+
+    // most threatening first, so the first one gets the loud colour
+    for (i, track) in tracks.0.iter().enumerate() {
+        let Some(pos) = track.position_at(clock.elapsed, gravity.0) else {
+            continue;
+        };
+        // keep the markers about the same size on screen
+        let size = pos.distance(cam_pos) * 0.02;
+        let color = if i == 0 {Color::srgb(1., 0.2, 0.2)} else {Color::srgb(1., 0.7, 0.1)};
+        gizmos.rect(Isometry3d::new(pos, facing), Vec2::splat(size), color);
+
+        for barrel_gt in &barrel_q {
+            let muzzle = barrel_gt.translation() + barrel_gt.forward() * MUZZLE_OFFSET;
+            if let Some(lead) = lead_point(track, muzzle, speed, gravity.0, clock.elapsed) {
+                gizmos.circle(Isometry3d::new(lead, facing), size * 0.4, color);
+                gizmos.line(pos, lead, color.with_alpha(0.3));
+            }
+        }
+    }
+}
+
+// ---
+
+/// Where a barrel at `muzzle` has to aim to meet `track`.
+fn lead_point(track: &Track, muzzle: Vec3, speed: f32, gravity: Vec3, now: f32) -> Option<Vec3> {
+    let pos = track.position_at(now, gravity)?;
+    let vel = track.velocity + gravity * (now - track.last_seen());
+    // bullets and balls fall alike, so the lead point ignores gravity
+    intercept(muzzle, speed, pos, vel, Vec3::ZERO).map(|(lead, _)| lead)
+}
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use bevy::prelude::*;
+
+use crate::fortress::FortressIntegrity;
+use crate::game::MatchClock;
+use crate::projectle::{BallHit, Intercepted, Landed, Thrown};
+use crate::radar::RadarTracks;
+use crate::shared::{Ammo, Headless, Interval};
+use crate::trebuchet::{Commanded, HoldFire, StateArming, StateIdle, StateLoose, StateTension, Trebuchet};
+use crate::turret::{BarrelTurret, Burst, Turret};
+use crate::turret_supply::Arsenal;
+use crate::GameState;
+
+// ---
+
+/// Counts what happens in the siege into `SiegeStats` and, with a window, shows it in a
+/// corner of the screen together with the fortress integrity and every trebuchet's state.
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<SiegeStats>()
+        .add_systems(Startup, startup.run_if(not(resource_exists::<Headless>)))
+        .add_systems(Update, refresh.run_if(not(resource_exists::<Headless>)).run_if(not(in_state(GameState::Loading))))
+        .add_systems(OnEnter(GameState::Results), report)
+        .add_observer(count_throw)
+        .add_observer(count_burst)
+        .add_observer(count_hit)
+        ;
+    }
+}
+
+// ---
+
+#[derive(Resource, Default, Debug)]
+pub struct SiegeStats {
+    pub balls_thrown: u32,
+    /// Thrown balls hit by defender fire before they landed.
+    pub balls_intercepted: u32,
+    pub rounds_fired: u32,
+    /// Rounds that hit a ball in flight.
+    pub rounds_hit: u32,
+    pub turrets: HashMap<Entity, TurretStats>,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TurretStats {
+    pub rounds: u32,
+    pub hits: u32,
+}
+
+impl TurretStats {
+    pub fn hit_ratio(&self) -> f32 {
+        if self.rounds > 0 {
+            self.hits as f32 / self.rounds as f32
+        } else {
+            0.
+        }
+    }
+}
+
+#[derive(Component)]
+struct HudText;
+
+const HUD_FONT_SIZE: f32 = 14.;
+
+// ---
+
+fn startup(
+    mut cmd: Commands,
+) {
+    cmd.spawn((
+        Text::default(),
+        TextFont {
+            font_size: HUD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::srgb(0.8, 1., 0.85)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        HudText,
+    ));
+}
+
+// ---
+
+fn count_throw(
+    _tr: Trigger<OnAdd, Thrown>,
+    mut stats: ResMut<SiegeStats>,
+) {
+    // fragments and other balls spawned in flight aren't throws
+    stats.balls_thrown += 1;
+}
+
+// ---
+
+fn count_burst(
+    tr: Trigger<Burst>,
+    barrel_q: Query<&BarrelTurret>,
+    mut stats: ResMut<SiegeStats>,
+) {
+    let rounds = tr.event().rounds as u32;
+    stats.rounds_fired += rounds;
+    if let Ok(barrel_turret) = barrel_q.get(tr.event().barrel) {
+        stats.turrets.entry(barrel_turret.0).or_default().rounds += rounds;
+    }
+}
+
+// ---
+
+fn count_hit(
+    tr: Trigger<BallHit>,
+    ball_q: Query<(Has<Intercepted>, Has<Thrown>), Without<Landed>>,
+    barrel_q: Query<&BarrelTurret>,
+    mut stats: ResMut<SiegeStats>,
+    mut cmd: Commands,
+) {
+    let event = tr.event();
+    // a ball lying on the field is no interception
+    let Ok((intercepted, thrown)) = ball_q.get(event.ball) else {
+        return;
+    };
+    if thrown && !intercepted {
+        stats.balls_intercepted += 1;
+        cmd.entity(event.ball).insert(Intercepted);
+    }
+    stats.rounds_hit += 1;
+    if let Some(barrel_turret) = event.by.and_then(|b| barrel_q.get(b).ok()) {
+        stats.turrets.entry(barrel_turret.0).or_default().hits += 1;
+    }
+}
+
+// ---
+
+fn refresh(
+    mut hud: Single<&mut Text, With<HudText>>,
+    stats: Res<SiegeStats>,
+    clock: Res<MatchClock>,
+    state: Res<State<GameState>>,
+    integrity: Option<Res<FortressIntegrity>>,
+    tracks: Option<Res<RadarTracks>>,
+    arsenal: Option<Res<Arsenal>>,
+    turret_q: Query<(Entity, &Transform), With<Turret>>,
+    treb_q: Query<
+        (
+            &Transform, Option<&Interval>, Option<&Ammo>,
+            Has<StateIdle>, Has<StateTension>, Has<StateArming>, Has<StateLoose>,
+            Has<HoldFire>, Has<Commanded>,
+        ),
+        With<Trebuchet>
+    >,
+) {
+    let mut s = String::new();
+    let minutes = (clock.elapsed / 60.) as u32;
+    let _ = writeln!(s, "{:?}  {}:{:04.1}", state.get(), minutes, clock.elapsed - minutes as f32 * 60.);
+    if let Some(integrity) = integrity {
+        let _ = writeln!(s, "Fortress integrity: {:.0}%", (1. - integrity.destroyed()) * 100.);
+    }
+    let _ = writeln!(s, "Balls thrown: {}  intercepted: {}", stats.balls_thrown, stats.balls_intercepted);
+    let overall = TurretStats { rounds: stats.rounds_fired, hits: stats.rounds_hit };
+    let _ = writeln!(s, "Rounds fired: {}  hit: {} ({:.0}%)", overall.rounds, overall.hits, overall.hit_ratio() * 100.);
+    if let (Some(tracks), Some(arsenal)) = (tracks, arsenal) {
+        let _ = writeln!(s, "Radar tracks: {}  arsenal: {}", tracks.0.len(), arsenal.rounds);
+    }
+
+    // left to right, as seen from behind the trebuchets
+    let mut turrets: Vec<_> = turret_q.iter().collect();
+    turrets.sort_by(|a, b| a.1.translation.x.total_cmp(&b.1.translation.x).then(a.0.cmp(&b.0)));
+    for (i, (turret_e, _)) in turrets.iter().enumerate() {
+        let t = stats.turrets.get(turret_e).copied().unwrap_or_default();
+        let _ = writeln!(s, "Turret {}: {}/{} rounds hit ({:.0}%)", i + 1, t.hits, t.rounds, t.hit_ratio() * 100.);
+    }
+
+    let mut trebs: Vec<_> = treb_q.iter().collect();
+    trebs.sort_by(|a, b| a.0.translation.x.total_cmp(&b.0.translation.x));
+    for (i, (_, interval, ammo, idle, tension, arming, loose, held, commanded)) in trebs.into_iter().enumerate() {
+        let _ = write!(s, "Trebuchet {:>2}: ", i + 1);
+        if tension {
+            s.push_str("Tension");
+        } else if arming {
+            s.push_str("Arming");
+        } else if loose {
+            s.push_str("Loose");
+        } else if idle {
+            let _ = write!(s, "Idle {:.1}s", interval.map_or(0., |i| i.0.remaining_secs()));
+        } else {
+            s.push('-');
+        }
+        if held {
+            s.push_str(" held");
+        }
+        if commanded {
+            s.push_str(" commanded");
+        }
+        if let Some(ammo) = ammo {
+            let _ = write!(s, "  ammo {}", ammo.0);
+        }
+        s.push('\n');
+    }
+    hud.0 = s;
+}
+
+// ---
+
+fn report(
+    stats: Res<SiegeStats>,
+) {
+    info!("Siege stats: {:?}", *stats);
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashSet, time::Duration};
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::fortress::{Damage, Health};
+use crate::projectle::{ProjectleKey, ProjectleOwner, ProjectleRegistry, ProjectleSpawn, Recycle, Released};
+use crate::rng::SiegeRng;
+use crate::GameState;
+
+// ---
+
+/// What a released projectile does to its surroundings the first time it touches something.
+/// Consumes the projectile.
+pub struct ImpactPlugin;
+impl Plugin for ImpactPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(FixedUpdate, detonate.run_if(on_event::<CollisionStarted>))
+        .add_systems(FixedUpdate, burn.run_if(any_with_component::<Burning>).run_if(in_state(GameState::Siege)))
+        ;
+    }
+}
+
+// ---
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub enum ImpactEffect {
+    /// Pushes every dynamic body within `radius` away from the impact and damages fortress
+    /// pieces, both falling off linearly with distance.
+    Explosive {
+        radius: f32,
+        impulse: f32,
+        damage: f32,
+    },
+    /// Splits into `count` projectiles of kind `key`, thrown out at `speed` around the
+    /// direction the parent was flying.
+    Fragment {
+        key: ProjectleKey,
+        count: u32,
+        speed: f32,
+        /// Half-angle of the spray cone, radians.
+        spread: f32,
+    },
+    /// Sets fortress pieces within `radius` burning for `secs` seconds at `dps` damage per second.
+    Incendiary {
+        radius: f32,
+        dps: f32,
+        secs: f32,
+    },
+}
+
+/// Damage over time left by an incendiary projectile.
+#[derive(Component)]
+pub struct Burning {
+    pub dps: f32,
+    pub timer: Timer,
+}
+
+// ---
+
+fn detonate(
+    mut collision_events: EventReader<CollisionStarted>,
+    proj_q: Query<(&Position, &LinearVelocity, &ImpactEffect, Option<&ProjectleOwner>), With<Released>>,
+    mut body_q: Query<(&RigidBody, &Position, Option<&mut ExternalImpulse>)>,
+    collider_parent_q: Query<&ColliderParent>,
+    piece_q: Query<&Position, With<Health>>,
+    spatial: SpatialQuery,
+    registry: Res<ProjectleRegistry>,
+    mut rng: ResMut<SiegeRng>,
+    mut cmd: Commands,
+) {
+    // a projectile can start touching several things in the same step
+    let mut spent = HashSet::new();
+    for CollisionStarted(e1, e2) in collision_events.read() {
+        for e in [*e1, *e2] {
+            if spent.contains(&e) {
+                continue;
+            }
+            let Ok((pos, vel, effect, owner)) = proj_q.get(e) else {
+                continue;
+            };
+            spent.insert(e);
+
+            match effect {
+                ImpactEffect::Explosive { radius, impulse, damage } => {
+                    for hit in within(&spatial, pos.0, *radius, &collider_parent_q) {
+                        if hit == e {
+                            continue;
+                        }
+                        if let Ok((rb, body_pos, ext)) = body_q.get_mut(hit) {
+                            let offset = body_pos.0 - pos.0;
+                            let falloff = 1. - (offset.length() / radius).min(1.);
+                            if rb.is_dynamic() && falloff > 0. {
+                                let push = offset.normalize_or(Vec3::Y) * impulse * falloff;
+                                match ext {
+                                    Some(mut ext) => { ext.apply_impulse(push); },
+                                    None => { cmd.entity(hit).insert(ExternalImpulse::new(push)); },
+                                }
+                            }
+                        }
+                        if let Ok(piece_pos) = piece_q.get(hit) {
+                            let falloff = 1. - (piece_pos.0.distance(pos.0) / radius).min(1.);
+                            cmd.trigger_targets(Damage(damage * falloff), hit);
+                        }
+                    }
+                },
+                ImpactEffect::Fragment { key, count, speed, spread } => {
+                    let Some(def) = registry.get(key) else {
+                        warn!("Unknown fragment projectile {:?}", key);
+                        cmd.trigger_targets(Recycle, e);
+                        continue;
+                    };
+                    let heading = Dir3::new(vel.0.with_y(vel.0.y.abs())).unwrap_or(Dir3::Y);
+                    let (u, v) = heading.any_orthonormal_pair();
+                    for _ in 0..*count {
+                        let angle = rng.f32() * spread;
+                        let around = rng.f32() * std::f32::consts::TAU;
+                        let dir = *heading * angle.cos() + (u * around.cos() + v * around.sin()) * angle.sin();
+                        cmd.trigger(ProjectleSpawn {
+                            key: key.clone(),
+                            pos: pos.0 + dir,
+                            dir: None,
+                            impulse: Some(dir * speed * def.mass()),
+                            lifetime: None,
+                            owner: owner.map(|o| o.0),
+                        });
+                    }
+                },
+                ImpactEffect::Incendiary { radius, dps, secs } => {
+                    for hit in within(&spatial, pos.0, *radius, &collider_parent_q) {
+                        if piece_q.contains(hit) {
+                            cmd.entity(hit).insert(Burning {
+                                dps: *dps,
+                                timer: Timer::new(Duration::from_secs_f32(*secs), TimerMode::Once),
+                            });
+                        }
+                    }
+                },
+            }
+            cmd.trigger_targets(Recycle, e);
+        }
+    }
+}
+
+/// Rigid bodies with a collider overlapping the sphere at `center`, in a stable order.
+fn within(
+    spatial: &SpatialQuery,
+    center: Vec3,
+    radius: f32,
+    collider_parent_q: &Query<&ColliderParent>,
+) -> Vec<Entity> {
+    let mut bodies: Vec<Entity> = spatial
+        .shape_intersections(&Collider::sphere(radius), center, Quat::IDENTITY, &SpatialQueryFilter::default())
+        .into_iter()
+        .map(|c| collider_parent_q.get(c).map_or(c, |p| p.get()))
+        .collect();
+    bodies.sort();
+    bodies.dedup();
+    bodies
+}
+
+// ---
+
+fn burn(
+    mut piece_q: Query<(Entity, &mut Burning)>,
+    mut cmd: Commands,
+    time: Res<Time>,
+) {
+    for (e, mut burning) in &mut piece_q {
+        cmd.trigger_targets(Damage(burning.dps * time.delta_secs()), e);
+        if burning.timer.tick(time.delta()).finished() {
+            cmd.entity(e).remove::<Burning>();
+        }
+    }
+}
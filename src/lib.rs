@@ -0,0 +1,89 @@
+// system signatures are long by nature in Bevy
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+pub mod shared;
+pub mod camera;
+pub mod env;
+pub mod trebuchet;
+// mod ball;
+pub mod field;
+// mod dummies;
+// mod dummy_ball;
+pub mod fortress;
+pub mod radar;
+pub mod animator;
+pub mod turret;
+// mod bullet;
+pub mod projectle;
+pub mod ron_asset;
+pub mod trebuchet_config;
+pub mod sim;
+pub mod game;
+pub mod ballistics;
+pub mod range_table;
+pub mod calibration;
+pub mod rng;
+pub mod replay;
+pub mod impact;
+pub mod tracer;
+pub mod turret_supply;
+pub mod fire_control;
+pub mod defense;
+pub mod attack;
+pub mod commander;
+pub mod gunner;
+pub mod attack_ai;
+pub mod hud;
+
+// ---
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum GameState {
+    #[default]
+    Loading,
+    Countdown,
+    Siege,
+    Paused,
+    Victory,
+    Defeat,
+    Results,
+    /// Watching a recorded siege, nothing is simulated.
+    Replay
+}
+
+#[derive(Component)]
+pub struct NotReady;
+
+#[derive(Component)]
+pub struct ShowGizmos;
+
+// ---
+
+/// Everything needed for a siege on top of `DefaultPlugins` and avian's `PhysicsPlugins`.
+/// Members can be disabled or swapped through the usual `PluginGroupBuilder` API.
+pub struct SiegePlugins;
+impl PluginGroup for SiegePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+        .add(game::GamePlugin)
+        .add(camera::CameraPlugin)
+        .add(env::EnvPlugin)
+        .add(field::FieldPlugin)
+        .add(fortress::FortressPlugin)
+        .add(radar::RadarPlugin)
+        .add(turret::TurretPlugin)
+        .add(tracer::TracerPlugin)
+        .add(turret_supply::TurretSupplyPlugin)
+        .add(fire_control::FireControlPlugin)
+        .add(defense::DefensePlugin)
+        .add(trebuchet::TrebuchetPlugin)
+        .add(attack::AttackPlugin)
+        .add(attack_ai::AttackerAiPlugin)
+        .add(projectle::ProjectlePlugin)
+        .add(impact::ImpactPlugin)
+        .add(animator::AnimatorPlugin)
+        .add(hud::HudPlugin)
+    }
+}
@@ -1,6 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 use bevy::{
-    // log::{Level, LogPlugin}, 
+    // log::Level, 
+    log::LogPlugin,
     prelude::*, 
     // window::{
     //     // WindowMode, 
@@ -18,100 +19,86 @@ use avian3d::{
 
 // use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-mod shared;
-mod camera;
-mod env;
-mod trebuchet;
-// mod ball;
-mod field;
-// mod dummies;
-// mod dummy_ball;
-mod fortress;
-mod radar;
-mod animator;
-mod turret;
-// mod bullet;
-mod projectle;
-
-// ---
-
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
-pub enum GameState {
-    #[default]
-    Loading,
-    Game
-}
-
-#[derive(Component)]
-pub struct NotReady;
-
-#[derive(Component)]
-pub struct ShowGizmos;
+use siege::{
+    calibration::CalibrationPlugin,
+    commander::CommanderPlugin,
+    gunner::GunnerPlugin,
+    replay::{ReplayPlayerPlugin, ReplayRecordPlugin},
+    rng::SiegeRng,
+    turret::{DefaultFireMode, FireMode},
+    sim::{SiegeSimPlugin, SimSettings},
+    SiegePlugins
+};
 
 // ---
 
 fn main() {
-    App::new()
-    .insert_resource(ClearColor(Color::BLACK))
-    .add_plugins((
-        DefaultPlugins
-        .set(
-            WindowPlugin {
-                primary_window : Some(Window {
-                    canvas: Some("#siege-canvas".into()),
-                    // resolution : WindowResolution::new(1400., 900.),
-                    // mode: WindowMode::BorderlessFullscreen,
-                    // position: WindowPosition::Centered(MonitorSelection::Primary),
+    let mut app = App::new();
+    app.insert_resource(SiegeRng::from_args());
+    if std::env::args().any(|a| a == "--tracers") {
+        app.insert_resource(DefaultFireMode(FireMode::Tracers));
+    }
+    if let Some(player) = ReplayPlayerPlugin::from_args() {
+        app
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_plugins((DefaultPlugins, player))
+        ;
+        app.run();
+        return;
+    }
+    if std::env::args().any(|a| a == "--calibrate") {
+        app.add_plugins((LogPlugin::default(), CalibrationPlugin));
+    } else if std::env::args().any(|a| a == "--headless") {
+        app
+        .insert_resource(SimSettings::from_args())
+        .add_plugins((LogPlugin::default(), SiegeSimPlugin))
+        ;
+    } else {
+        app
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_plugins((
+            DefaultPlugins
+            .set(
+                WindowPlugin {
+                    primary_window : Some(Window {
+                        canvas: Some("#siege-canvas".into()),
+                        // resolution : WindowResolution::new(1400., 900.),
+                        // mode: WindowMode::BorderlessFullscreen,
+                        // position: WindowPosition::Centered(MonitorSelection::Primary),
+                        ..default()
+                    }),
                     ..default()
-                }),
-                ..default()
-            },
-        ),
-        // .set(
-        //     LogPlugin {
-        //         level: Level::INFO,
-        //         filter: "wgpu=error,naga=warn,bevy_gltf_components::ronstring_to_reflect_component=error,avian3d::collision::narrow_phase=error,wgpu_hal::vulkan::instance=error".to_string(),
-        //         ..default()
-        //     }
-        // )
-
-        PhysicsPlugins::default(),
-        // PhysicsDebugPlugin::default(),
-        // WorldInspectorPlugin::new(),
-        camera::CameraPlugin,
-        env::EnvPlugin,
-        trebuchet::TrebuchetPlugin,
-        
-        field::FieldPlugin,
-        // dummies::DummiesPlugin,
-        fortress::FortressPlugin,
-        radar:: RadarPlugin,
-        animator::AnimatorPlugin,
-        turret::TurretPlugin,
-        projectle::ProjectlePlugin,
-        // ball::BallPlugin,
-        // bullet::BulletPlugin
-        // dummy_ball::DBallPlugin,
- 
+                },
+            ),
+            // .set(
+            //     LogPlugin {
+            //         level: Level::INFO,
+            //         filter: "wgpu=error,naga=warn,bevy_gltf_components::ronstring_to_reflect_component=error,avian3d::collision::narrow_phase=error,wgpu_hal::vulkan::instance=error".to_string(),
+            //         ..default()
+            //     }
+            // )
 
-
-    ))
-    .init_state::<GameState>()
-    .add_systems(Update, check_ready.run_if(in_state(GameState::Loading)))
-    // .add_systems(Update, show_gizmos)
-    .run();
-}
-
-// ---
-
-fn check_ready(
-    not_ready_q: Query<&NotReady>,
-    mut next: ResMut<NextState<GameState>>,
-) {
-    if not_ready_q.is_empty() {
-        info!("GAME!");
-        next.set(GameState::Game);
-    } 
+            PhysicsPlugins::default(),
+            // PhysicsDebugPlugin::default(),
+            // WorldInspectorPlugin::new(),
+            SiegePlugins,
+        ))
+        // .add_systems(Update, show_gizmos)
+        ;
+        // both take the left button and X, so only one of them runs
+        if std::env::args().any(|a| a == "--commander") {
+            app.add_plugins(CommanderPlugin);
+            if std::env::args().any(|a| a == "--gunner") {
+                warn!("--gunner can't be used with --commander, ignoring it");
+            }
+        } else if std::env::args().any(|a| a == "--gunner") {
+            app.add_plugins(GunnerPlugin);
+        }
+    }
+    if let Some(recorder) = ReplayRecordPlugin::from_args() {
+        app.add_plugins(recorder);
+    }
+    app.run();
 }
 
 // ---
@@ -124,4 +111,4 @@ fn show_gizmos (
     for t in t_q.iter()   {
         gismos.axes(*t, 10.);
     }
-}
\ No newline at end of file
+}
@@ -1,21 +1,32 @@
-use std::{collections::HashMap, time::Duration};
+use std::{borrow::Cow, collections::{HashMap, HashSet}, time::Duration};
 
 use bevy::{
     prelude::*,
     pbr::{NotShadowCaster, NotShadowReceiver}
 };
+use serde::{Deserialize, Serialize};
 
 use avian3d::prelude::*;
-use crate::shared::GameLayer; 
+use crate::{fortress::PieceMass, impact::ImpactEffect, ron_asset::RonAssetPlugin, shared::{GameLayer, Targetable}, GameState, NotReady};
 
 pub struct ProjectlePlugin;
 impl Plugin for ProjectlePlugin {
     fn build(&self, app: &mut App) {
         app
+        .add_plugins(RonAssetPlugin::<ProjectleRegistry>::new(&["projectles.ron"]))
+        .init_resource::<ProjectleRegistry>()
+        .init_resource::<Projectles>()
+        .init_resource::<ProjectlePool>()
+        .init_resource::<PoolStats>()
         .add_systems(Startup, startup)
-        .add_systems(Update, despawn_on_time.run_if(any_with_component::<LifeTime>))
-        .add_systems(Update, despawn_on_collision.run_if(on_event::<CollisionEnded>))
+        .add_systems(Update, registry_ready.run_if(any_with_component::<ProjectleRegistryTMP>))
+        .add_systems(Update, apply_registry.run_if(on_event::<AssetEvent<ProjectleRegistry>>))
+        .add_systems(FixedUpdate, despawn_on_time.run_if(any_with_component::<LifeTime>).run_if(not(in_state(GameState::Loading))))
+        .add_systems(FixedUpdate, despawn_on_collision.run_if(on_event::<CollisionEnded>))
+        .add_systems(FixedUpdate, (report_impact, report_hits).run_if(on_event::<CollisionStarted>))
+        .add_systems(OnEnter(GameState::Results), report_pool)
         .add_observer(spawn)
+        .add_observer(recycle)
         ;
     }
 }
@@ -33,26 +44,195 @@ pub struct  Projectle;
 #[derive(Component)]
 pub struct  Released;
 
+/// Let go by a trebuchet's sling, unlike fragments and other balls spawned in flight.
+#[derive(Component)]
+pub struct Thrown;
+
 #[derive(Component)]
 pub struct LifeTime(pub Timer);
 
-pub struct ProjectleMM(Handle<Mesh>, Handle<StandardMaterial>);
+/// The trebuchet or barrel that launched the projectile.
+#[derive(Component)]
+pub struct ProjectleOwner(pub Entity);
+
+/// A thrown ball first touched something other than a projectile.
+#[derive(Event)]
+pub struct BallImpact {
+    pub ball: Entity,
+    /// The trebuchet that threw it.
+    pub owner: Entity,
+    pub pos: Vec3,
+    /// What it touched is a piece of the fortress.
+    pub on_fortress: bool,
+}
+
+/// Defender fire hit a ball: a bullet touched it or a tracer's ray met it.
+#[derive(Event)]
+pub struct BallHit {
+    pub ball: Entity,
+    /// The barrel that fired.
+    pub by: Option<Entity>,
+}
+
+/// Defender fire has hit the ball in flight.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Intercepted;
+
+/// The ball has reported its `BallImpact`.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Landed;
 
-#[derive(Hash, PartialEq, Eq, Debug)]
-pub enum ProjectleKey {
+/// The bullet has reported its `BallHit`, later contacts don't count.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Scored;
+
+/// Name of a projectile kind in the `ProjectleRegistry`.
+#[derive(Component, Clone, Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProjectleKey(pub Cow<'static, str>);
+
+impl ProjectleKey {
+    pub const BALL: Self = Self(Cow::Borrowed("ball"));
+    pub const BULLET: Self = Self(Cow::Borrowed("bullet"));
+}
+
+/// Which side's markers and collision layer a projectile gets: `Ball`s are thrown by the
+/// attackers and hunted by turrets, `Bullet`s are fired by the defenders.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ProjectleRole {
     Ball,
-    Bullet
+    Bullet,
+}
+
+/// What happens once a released projectile stops touching something.
+#[derive(Component, Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum OnHit {
+    /// Despawn right after the first contact.
+    #[default]
+    Expire,
+    /// Keep bouncing and rolling until the lifetime runs out.
+    Persist,
+}
+
+/// One projectile kind, as declared in `*.projectles.ron`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectleDef {
+    pub radius: f32,
+    pub density: f32,
+    pub color: Color,
+    /// Glow instead of a lit surface, for tracers and the like.
+    #[serde(default)]
+    pub emissive: bool,
+    pub role: ProjectleRole,
+    /// Seconds before despawn when `ProjectleSpawn::lifetime` is not given.
+    #[serde(default)]
+    pub lifetime: Option<u64>,
+    #[serde(default)]
+    pub restitution: f32,
+    #[serde(default)]
+    pub on_hit: OnHit,
+    /// Blast, fragments or fire on the first contact after release, see `ImpactPlugin`.
+    #[serde(default)]
+    pub effect: Option<ImpactEffect>,
+}
+
+impl ProjectleDef {
+    pub fn mass(&self) -> f32 {
+        self.density * 4. / 3. * std::f32::consts::PI * self.radius.powi(3)
+    }
+}
+
+/// Every projectile kind that can be spawned, keyed by name.
+#[derive(Resource, Asset, TypePath, Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProjectleRegistry(pub HashMap<ProjectleKey, ProjectleDef>);
+
+impl Default for ProjectleRegistry {
+    fn default() -> Self {
+        Self(HashMap::from([
+            (ProjectleKey::BALL, ProjectleDef {
+                radius: BALL_RADIUS,
+                density: BALL_DENSITY,
+                color: Color::hsl(150., 1.0, 0.5),
+                emissive: false,
+                role: ProjectleRole::Ball,
+                lifetime: None,
+                restitution: 0.,
+                on_hit: OnHit::Expire,
+                effect: None,
+            }),
+            (ProjectleKey::BULLET, ProjectleDef {
+                radius: BULLET_RADIUS,
+                density: BULLET_DENSITY,
+                color: Color::hsl(47., 1.0, 0.5),
+                emissive: true,
+                role: ProjectleRole::Bullet,
+                lifetime: Some(2),
+                restitution: 0.,
+                on_hit: OnHit::Expire,
+                effect: None,
+            }),
+        ]))
+    }
+}
+
+impl ProjectleRegistry {
+    pub fn get(&self, key: &ProjectleKey) -> Option<&ProjectleDef> {
+        self.0.get(key)
+    }
 }
+
 #[derive(Resource)]
+pub struct ProjectleRegistryHandle(pub Handle<ProjectleRegistry>);
+
+pub const PROJECTLE_REGISTRY_PATH: &str = "configs/default.projectles.ron";
+
+/// Keeps the match in Loading until the registry file is in, see `registry_ready`.
+#[derive(Component)]
+struct ProjectleRegistryTMP;
+
+pub struct ProjectleMM(Handle<Mesh>, Handle<StandardMaterial>);
+
+/// Mesh and material per kind, made on first spawn and dropped when the registry changes.
+#[derive(Resource, Default)]
 pub struct Projectles(HashMap<ProjectleKey, ProjectleMM>);
 
+/// Idle projectile entities per kind, kept with physics disabled and hidden until the next spawn.
+#[derive(Resource, Default)]
+pub struct ProjectlePool(HashMap<ProjectleKey, Vec<Entity>>);
+
+#[derive(Resource, Default, Debug)]
+pub struct PoolStats {
+    /// Entities created because the pool had none of the kind.
+    pub created: u32,
+    /// Spawns served from the pool.
+    pub reused: u32,
+    /// Projectiles returned to the pool.
+    pub recycled: u32,
+    /// Projectiles despawned because their kind's pool was full.
+    pub dropped: u32,
+}
+
+/// Marks a projectile entity sitting in the `ProjectlePool`.
+#[derive(Component)]
+pub struct Pooled;
+
+/// Takes a projectile out of play, triggered on the projectile entity.
+/// Use it instead of despawning so the entity can be reused.
+#[derive(Event)]
+pub struct Recycle;
+
 #[derive(Event)]
 pub struct ProjectleSpawn {
     pub key: ProjectleKey,
     pub pos: Vec3,
     pub dir: Option<Dir3>,
     pub impulse: Option<Vec3>,
-    pub lifetime: Option<u64>
+    pub lifetime: Option<u64>,
+    pub owner: Option<Entity>
 }
 
 // ---
@@ -63,39 +243,65 @@ pub const BALL_RADIUS: f32 = 0.55;
 pub const BULLET_DENSITY: f32 = 1.;
 pub const BULLET_RADIUS: f32 = 0.5;
 
+/// Idle entities kept per kind, extra ones are despawned.
+const POOL_CAPACITY: usize = 512;
+
 // ---
 
 
 fn startup (
     mut cmd: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<AssetServer>,
+) {
+    cmd.insert_resource(ProjectleRegistryHandle(assets.load(PROJECTLE_REGISTRY_PATH)));
+    cmd.spawn((NotReady, ProjectleRegistryTMP));
+}
+
+// ---
 
+/// Takes the file's kinds before the siege starts, so no ball or bullet is made, and no sling
+/// sized, from the built-in ones.
+fn registry_ready(
+    tmp_q: Single<Entity, With<ProjectleRegistryTMP>>,
+    registries: Res<Assets<ProjectleRegistry>>,
+    handle: Res<ProjectleRegistryHandle>,
+    assets: Res<AssetServer>,
+    mut registry: ResMut<ProjectleRegistry>,
+    mut cmd: Commands,
 ) {
-    cmd.insert_resource(Projectles(
-        HashMap::from([
-            (
-                ProjectleKey::Ball,
-                ProjectleMM(
-                    meshes.add(Sphere::new(BALL_RADIUS)),
-                    materials.add(Color::hsl(150., 1.0, 0.5))
-                )
-            ),
-            (
-                ProjectleKey::Bullet,
-                ProjectleMM(
-                    meshes.add(Sphere::new(BULLET_RADIUS)),
-                    // materials.add(Color::hsl(0., 1.0, 0.5))
-                    materials.add(StandardMaterial {
-                        emissive: LinearRgba::from(Color::hsl(47., 1.0, 0.5)),
-                        ..default()
-                    })
-
-                )
-            )
-
-        ])
-    ));
+    match registries.get(&handle.0) {
+        Some(loaded) => *registry = loaded.clone(),
+        None if assets.load_state(&handle.0).is_failed() => {
+            warn!("No projectiles at {PROJECTLE_REGISTRY_PATH}, using the built-in ones");
+        },
+        None => return,
+    }
+    cmd.entity(tmp_q.into_inner()).despawn();
+}
+
+// ---
+
+fn apply_registry(
+    mut events: EventReader<AssetEvent<ProjectleRegistry>>,
+    registries: Res<Assets<ProjectleRegistry>>,
+    handle: Res<ProjectleRegistryHandle>,
+    mut registry: ResMut<ProjectleRegistry>,
+    mut projectles: ResMut<Projectles>,
+    mut pool: ResMut<ProjectlePool>,
+    mut cmd: Commands,
+) {
+    for event in events.read() {
+        if event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0) {
+            if let Some(loaded) = registries.get(&handle.0) {
+                *registry = loaded.clone();
+                projectles.0.clear();
+                // pooled entities carry the old meshes
+                for e in pool.0.drain().flat_map(|(_, free)| free) {
+                    cmd.entity(e).despawn();
+                }
+            }
+        }
+    }
 }
 
 // ---
@@ -103,64 +309,149 @@ fn startup (
 fn spawn(
     trigger: Trigger<ProjectleSpawn>,
     mut cmd: Commands,
-    projectles: Res<Projectles> 
+    registry: Res<ProjectleRegistry>,
+    mut projectles: ResMut<Projectles>,
+    mut pool: ResMut<ProjectlePool>,
+    mut stats: ResMut<PoolStats>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     let event = trigger.event();
-    let conf = projectles.0.get(&event.key).unwrap();
-    let id = cmd.spawn((
+    let Some(def) = registry.get(&event.key) else {
+        warn!("Unknown projectile {:?}", event.key);
+        return;
+    };
+    let conf = projectles.0.entry(event.key.clone()).or_insert_with(|| ProjectleMM(
+        meshes.add(Sphere::new(def.radius)),
+        materials.add(if def.emissive {
+            StandardMaterial {
+                emissive: LinearRgba::from(def.color),
+                ..default()
+            }
+        } else {
+            def.color.into()
+        })
+    ));
+
+    let id = match pool.0.get_mut(&event.key).and_then(|free| free.pop()) {
+        Some(e) => {
+            stats.reused += 1;
+            cmd.entity(e).remove::<(Pooled, RigidBodyDisabled, ColliderDisabled)>();
+            e
+        },
+        None => {
+            stats.created += 1;
+            cmd.spawn_empty().id()
+        }
+    };
+
+    cmd.entity(id).insert((
         Mesh3d(conf.0.clone()),
         MeshMaterial3d(conf.1.clone()),
         RigidBody::Dynamic,
-        Restitution::new(0.).with_combine_rule(CoefficientCombine::Min),
+        Collider::sphere(def.radius),
+        ColliderDensity(def.density),
+        // nothing else on the field bounces, so the projectile's own value decides
+        Restitution::new(def.restitution).with_combine_rule(CoefficientCombine::Max),
         NotShadowCaster,
         NotShadowReceiver,
         Projectle,
-    
-    ))
-    .id()
-    ;
-    
-    if event.key == ProjectleKey::Ball {
-        cmd.entity(id).insert((
-            Collider::sphere(BALL_RADIUS), ColliderDensity(BALL_DENSITY), Ball, Name::new("Ball"), CollisionLayers::new(GameLayer::Attacker, [LayerMask::ALL]),
-        ));
-    } else {
-        cmd.entity(id).insert((
-            Collider::sphere(BULLET_RADIUS), ColliderDensity(BULLET_DENSITY), Bullet, Name::new("Bullet"), CollisionLayers::new(GameLayer::Defender, [LayerMask::ALL])
-        ));
+        event.key.clone(),
+        def.on_hit,
+        Visibility::Inherited,
+    ));
+
+    match def.role {
+        ProjectleRole::Ball => cmd.entity(id).insert((
+            Ball, Name::new("Ball"), CollisionLayers::new(GameLayer::Attacker, [LayerMask::ALL]),
+        )),
+        ProjectleRole::Bullet => cmd.entity(id).insert((
+            Bullet, Name::new("Bullet"), CollisionLayers::new(GameLayer::Defender, [LayerMask::ALL])
+        )),
+    };
+
+    if let Some(effect) = &def.effect {
+        cmd.entity(id).insert(effect.clone());
+    }
+
+    if let Some(owner) = event.owner {
+        cmd.entity(id).insert(ProjectleOwner(owner));
     }
-    
 
     if let Some(imp) = event.impulse {
         cmd.entity(id).insert(ExternalImpulse::new(imp));
     }
 
-    let trans = Transform::from_translation(event.pos);
+    let mut trans = Transform::from_translation(event.pos);
     if let Some(dir) = event.dir {
-        cmd.entity(id).insert(trans.looking_to(dir, Vec3::Y));
-    } else {
-        cmd.entity(id).insert(trans);
+        trans = trans.looking_to(dir, Vec3::Y);
     }
-    if let Some(lt) = event.lifetime {
+    // a pooled entity keeps its old pose, which would win over the new transform
+    cmd.entity(id).insert((
+        trans,
+        Position(trans.translation),
+        Rotation(trans.rotation),
+        LinearVelocity::ZERO,
+        AngularVelocity::ZERO,
+    ));
+    if let Some(lt) = event.lifetime.or(def.lifetime) {
         cmd.entity(id).insert((
             LifeTime(Timer::new(Duration::from_secs(lt), TimerMode::Once)),
             Released
         ));
     }
-    
+}
 
+// ---
+
+fn recycle(
+    tr: Trigger<Recycle>,
+    key_q: Query<&ProjectleKey, Without<Pooled>>,
+    mut pool: ResMut<ProjectlePool>,
+    mut stats: ResMut<PoolStats>,
+    mut cmd: Commands,
+) {
+    let e = tr.entity();
+    let Ok(key) = key_q.get(e) else {
+        return;
+    };
+    // expiring and detonating in the same step recycles twice, `key_q` skips the second
+    let free = pool.0.entry(key.clone()).or_default();
+    if free.len() >= POOL_CAPACITY {
+        stats.dropped += 1;
+        cmd.entity(e).despawn_recursive();
+        return;
+    }
+    stats.recycled += 1;
+    free.push(e);
+    // physics is switched off rather than removed, so the body and avian's derived
+    // components stay put; gameplay markers go so no query mistakes it for a live projectile
+    cmd.entity(e)
+    .insert((RigidBodyDisabled, ColliderDisabled, LinearVelocity::ZERO, AngularVelocity::ZERO))
+    .remove::<(ExternalImpulse, ExternalForce, LifeTime, Released, Thrown, ProjectleOwner, ImpactEffect)>()
+    .remove::<(Ball, Bullet, Targetable, Landed, Intercepted, Scored)>()
+    .insert((Visibility::Hidden, Pooled))
+    ;
+}
+
+// ---
+
+fn report_pool(
+    stats: Res<PoolStats>,
+) {
+    info!("Projectile pool: {:?}", *stats);
 }
 
 // ---
 
 fn despawn_on_collision(
     mut collision_events: EventReader<CollisionEnded>,
-    mut t_q: Query<(Entity, &mut LifeTime), With<Released>>,
+    mut t_q: Query<(Entity, &mut LifeTime, &OnHit), With<Released>>,
 ) {
     for CollisionEnded(e1, e2) in collision_events.read() {
-        t_q.iter_mut().for_each(|(e, mut lt)| {
-            if e == *e1 || e == *e2 {
-                lt.0.set_duration(Duration::ZERO); 
+        t_q.iter_mut().for_each(|(e, mut lt, on_hit)| {
+            if (e == *e1 || e == *e2) && *on_hit == OnHit::Expire {
+                lt.0.set_duration(Duration::ZERO);
             }
         });
     }
@@ -168,6 +459,58 @@ fn despawn_on_collision(
 
 // ---
 
+fn report_impact(
+    mut collision_events: EventReader<CollisionStarted>,
+    ball_q: Query<(&Position, &ProjectleOwner), (With<Ball>, With<Thrown>, Without<Landed>)>,
+    projectle_q: Query<(), With<Projectle>>,
+    piece_q: Query<(), With<PieceMass>>,
+    mut cmd: Commands,
+) {
+    // a ball can start touching several things in the same step
+    let mut landed = HashSet::new();
+    for CollisionStarted(e1, e2) in collision_events.read() {
+        for (ball_e, other) in [(*e1, *e2), (*e2, *e1)] {
+            // bullets meet balls in the air, that's no landing
+            if landed.contains(&ball_e) || projectle_q.contains(other) {
+                continue;
+            }
+            let Ok((pos, owner)) = ball_q.get(ball_e) else {
+                continue;
+            };
+            landed.insert(ball_e);
+            cmd.entity(ball_e).insert(Landed);
+            cmd.trigger(BallImpact { ball: ball_e, owner: owner.0, pos: pos.0, on_fortress: piece_q.contains(other) });
+        }
+    }
+}
+
+// ---
+
+fn report_hits(
+    mut collision_events: EventReader<CollisionStarted>,
+    bullet_q: Query<Option<&ProjectleOwner>, (With<Bullet>, With<Released>, Without<Scored>)>,
+    ball_q: Query<(), (With<Ball>, With<Released>)>,
+    mut cmd: Commands,
+) {
+    // a bullet counts once, even if it grazes two balls in the same step
+    let mut spent = HashSet::new();
+    for CollisionStarted(e1, e2) in collision_events.read() {
+        for (bullet_e, ball_e) in [(*e1, *e2), (*e2, *e1)] {
+            if spent.contains(&bullet_e) || !ball_q.contains(ball_e) {
+                continue;
+            }
+            let Ok(owner) = bullet_q.get(bullet_e) else {
+                continue;
+            };
+            spent.insert(bullet_e);
+            cmd.entity(bullet_e).insert(Scored);
+            cmd.trigger(BallHit { ball: ball_e, by: owner.map(|o| o.0) });
+        }
+    }
+}
+
+// ---
+
 fn despawn_on_time(
     mut t_q: Query<(Entity, &mut LifeTime)>,
     mut cmd: Commands,
@@ -176,7 +519,7 @@ fn despawn_on_time(
     for (e, mut lt) in &mut t_q {
         lt.0.tick(time.delta());
         if lt.0.finished() {
-            cmd.entity(e).despawn();
+            cmd.trigger_targets(Recycle, e);
         }
     }
 }
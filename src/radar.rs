@@ -1,12 +1,16 @@
-use std::f32::consts::PI;
-use avian3d::prelude::{Collider, ShapeCastConfig, SpatialQuery, SpatialQueryFilter};
+use std::collections::VecDeque;
+use avian3d::prelude::Gravity;
 use bevy::scene::SceneInstanceReady;
 use bevy::{
+    animation::graph::AnimationNodeType,
     // gizmos::gizmos,
     prelude::*};
-use crate::{animator::*, GameState};
-use crate::shared::{GameLayer, SetTarget, Targetable};
+use crate::{animator::*, game::MatchClock, GameState};
+use crate::rng::SiegeRng;
+use crate::shared::{Headless, SetTarget, Targetable};
 use crate::field::FortressPosition;
+use crate::projectle::{ProjectleKey, ProjectleRegistry};
+use serde::{Deserialize, Serialize};
 
 
 pub struct RadarPlugin;
@@ -14,9 +18,13 @@ impl Plugin for RadarPlugin {
     fn build(&self, app: &mut App) {
         app
         // .register_type::<Antenna>()
-        .add_systems(Update, startup.run_if(resource_added::<FortressPosition>))
-        .add_systems(OnEnter(GameState::Game), enter_game)
-        .add_systems(Update, scan.run_if(in_state(GameState::Game)))
+        .init_resource::<RadarTracks>()
+        .add_systems(Startup, startup.run_if(not(resource_exists::<Headless>)))
+        .add_systems(FixedUpdate, sweep.before(RadarSet).run_if(not(in_state(GameState::Loading))))
+        .add_systems(Update, sync_sweep.run_if(resource_exists::<AllAnimations>).run_if(not(in_state(GameState::Loading))))
+        .add_systems(OnEnter(GameState::Countdown), enter_game)
+        .add_systems(FixedUpdate, (scan, update_tracks).chain().in_set(RadarSet).run_if(in_state(GameState::Siege)))
+        .add_observer(attach)
         .add_observer(targetable_despawn)
 
         ;
@@ -28,43 +36,199 @@ impl Plugin for RadarPlugin {
 #[derive(Component)]
 pub struct Radar;
 
-#[derive(Resource)]
-pub struct RadarPositions(pub Vec<Vec3>);
+/// Radar scanning and track upkeep, so fire control can run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RadarSet;
 
-#[derive(Resource)]
-pub struct RadarTargets(pub Vec<Entity>);
+/// Everything the radars are following, most threatening first.
+#[derive(Resource, Default)]
+pub struct RadarTracks(pub Vec<Track>);
+
+impl RadarTracks {
+    pub fn get(&self, entity: Entity) -> Option<&Track> {
+        self.0.iter().find(|t| t.entity == entity)
+    }
+}
+
+/// One contact as the radars see it: where it was, where it's going and how much it matters.
+#[derive(Clone, Debug)]
+pub struct Track {
+    pub entity: Entity,
+    /// (seconds into the siege, position) of the latest sightings, oldest first.
+    pub history: VecDeque<(f32, Vec3)>,
+    /// Estimated velocity at the last sighting.
+    pub velocity: Vec3,
+    /// Where the contact comes down if nothing stops it.
+    pub impact: Option<Vec3>,
+    /// Seconds from now until `impact`.
+    pub time_to_impact: Option<f32>,
+    /// Rough damage the contact does on landing: its mass times landing speed, scaled down
+    /// the farther it lands from the fortress.
+    pub predicted_damage: f32,
+    pub threat: f32,
+    /// Radars that have sighted the contact.
+    pub seen_by: Vec<Entity>,
+}
+
+impl Track {
+    fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            history: VecDeque::with_capacity(TRACK_HISTORY),
+            velocity: Vec3::ZERO,
+            impact: None,
+            time_to_impact: None,
+            predicted_damage: 0.,
+            threat: 0.,
+            seen_by: Vec::new(),
+        }
+    }
+
+    pub fn last_seen(&self) -> f32 {
+        self.history.back().map_or(0., |(t, _)| *t)
+    }
+
+    /// Position extrapolated from the last sighting to `now` under `gravity`.
+    pub fn position_at(&self, now: f32, gravity: Vec3) -> Option<Vec3> {
+        let (seen, pos) = *self.history.back()?;
+        let dt = now - seen;
+        Some(pos + self.velocity * dt + 0.5 * gravity * dt * dt)
+    }
+}
+
+/// Sightings kept per track.
+const TRACK_HISTORY: usize = 16;
+/// Seconds without a sighting before a track is dropped.
+const TRACK_TIMEOUT: f32 = 4.;
+/// Landing distance from the fortress at which predicted damage is halved.
+const THREAT_FALLOFF: f32 = 40.;
 
 #[derive(Component)]
 pub struct Antenna;
 
+/// The radar an antenna belongs to.
+#[derive(Component)]
+pub struct AntennaOf(pub Entity);
+
+/// Beam bearing from the radar's facing, radians, turned in `FixedUpdate` so sightings don't
+/// depend on frame timing. The antenna model is posed from it, not the other way round.
+#[derive(Component, Default)]
+pub struct Sweep(pub f32);
+
+/// What a radar can see. Targets are only looked at while inside the beam, which turns with
+/// the antenna; the chance of a sighting falls with range and reported positions get noisier.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RadarSensor {
+    /// Horizontal beam width, radians.
+    pub beam_width: f32,
+    /// Highest elevation above (and below) the antenna the beam covers, radians.
+    pub elevation: f32,
+    /// Antenna turn rate, rad/s.
+    pub sweep_speed: f32,
+    pub max_range: f32,
+    /// Chance of a sighting per pass of the beam over a target right at the radar.
+    pub p_detect: f32,
+    /// Range at which `p_detect` has halved.
+    pub half_range: f32,
+    /// Position noise at zero range, metres (standard deviation).
+    pub noise_floor: f32,
+    /// Extra position noise per metre of range.
+    pub noise_per_metre: f32,
+}
+
+impl Default for RadarSensor {
+    fn default() -> Self {
+        Self {
+            beam_width: 0.5,
+            elevation: 1.2,
+            sweep_speed: 2.,
+            max_range: 180.,
+            p_detect: 0.98,
+            half_range: 120.,
+            noise_floor: 0.2,
+            noise_per_metre: 0.003,
+        }
+    }
+}
+
+impl RadarSensor {
+    /// Chance of a sighting per pass of the beam at `range`.
+    pub fn probability(&self, range: f32) -> f32 {
+        if range > self.max_range {
+            return 0.;
+        }
+        self.p_detect / (1. + (range / self.half_range.max(f32::EPSILON)).powi(4))
+    }
+
+    /// `probability` spread over the steps a target spends in the beam on one pass.
+    fn step_probability(&self, range: f32, dt: f32) -> f32 {
+        let dwell = self.beam_width / self.sweep_speed.max(f32::EPSILON);
+        1. - (1. - self.probability(range)).powf(dt / dwell.max(dt))
+    }
+
+    fn noise(&self, range: f32, rng: &mut SiegeRng) -> Vec3 {
+        let sigma = self.noise_floor + self.noise_per_metre * range;
+        let (x, y) = rng.gaussian_pair();
+        let (z, _) = rng.gaussian_pair();
+        Vec3::new(x, y, z) * sigma
+    }
+}
+
 // ---
 
 fn startup(
-    mut cmd: Commands,
     assets: ResMut<AssetServer>,
     mut all_animations: ResMut<AllAnimations>,
     mut graphs: ResMut<Assets<AnimationGraph>>,
-    ftp: Res<FortressPosition>
 ) {
     all_animations.add(AnimationKey::Radar, "models/radar.glb", 2, &mut graphs, &assets);
-    let sh = assets.load(GltfAssetLabel::Scene(0).from_asset("models/radar.glb"));
-    let mut rp = RadarPositions(Vec::new());
-
-    for i in 0..2 {
-        let pos = ftp.0 + Vec3::X * (if i == 0 {-1.} else {1.} * 100.) + Vec3::Z * 100.; 
-        cmd.spawn((
-            SceneRoot(sh.clone()),
-            Transform::from_translation(pos.with_y(0.)).with_rotation(Quat::from_rotation_y(PI)),
-            Radar,
-            AnimationKey::Radar,
-            Name::new("Radar")
-        ))
-        .observe(setup)
-        ;
-        rp.0.push(pos);
+}
+
+// ---
+
+/// Gives a freshly placed radar its model, or a bare antenna when headless.
+fn attach(
+    tr: Trigger<OnAdd, Radar>,
+    headless: Option<Res<Headless>>,
+    assets: Res<AssetServer>,
+    mut cmd: Commands,
+) {
+    let radar_e = tr.entity();
+    cmd.entity(radar_e).insert(Sweep::default());
+    if headless.is_some() {
+        let antenna_e = cmd.spawn((Transform::from_xyz(0., 10., 0.), Antenna, AntennaOf(radar_e))).id();
+        cmd.entity(radar_e).add_child(antenna_e);
+        return;
+    }
+    cmd.entity(radar_e)
+    .insert((
+        SceneRoot(assets.load(GltfAssetLabel::Scene(0).from_asset("models/radar.glb"))),
+        AnimationKey::Radar,
+    ))
+    .observe(setup)
+    ;
+}
+
+// ---
+
+fn sweep(
+    mut radar_q: Query<(&mut Sweep, &RadarSensor)>,
+    mut ant_q: Query<(&mut Transform, &AntennaOf)>,
+    headless: Option<Res<Headless>>,
+    time: Res<Time>,
+) {
+    for (mut sweep, sensor) in &mut radar_q {
+        sweep.0 = (sweep.0 + sensor.sweep_speed * time.delta_secs()).rem_euclid(std::f32::consts::TAU);
+    }
+    // the bare antenna has no clip to play, see `sync_sweep` for the model
+    if headless.is_some() {
+        for (mut t, antenna_of) in &mut ant_q {
+            if let Ok((sweep, _)) = radar_q.get(antenna_of.0) {
+                t.rotation = Quat::from_rotation_y(sweep.0);
+            }
+        }
     }
-    cmd.insert_resource(rp);
-    cmd.insert_resource(RadarTargets(Vec::new()));
 }
 
 // ---
@@ -78,7 +242,7 @@ fn setup(
     for c in children.iter_descendants_depth_first(tr.entity()) {
         if let Ok(props) = extras.get(c) {
             if props.value.contains("Antenna") {
-                cmd.entity(c).insert(Antenna);
+                cmd.entity(c).insert((Antenna, AntennaOf(tr.entity())));
             }
         }
     } 
@@ -97,48 +261,192 @@ fn enter_game(
 // ---
 
 fn scan(
-    ant_q: Query<&GlobalTransform, With<Antenna>>,
-    spatial: SpatialQuery,
-    // mut gizmos: Gizmos,
+    ant_q: Query<(&GlobalTransform, &AntennaOf)>,
+    sensor_q: Query<(&RadarSensor, &GlobalTransform, &Sweep)>,
+    target_q: Query<(Entity, &GlobalTransform), With<Targetable>>,
     mut cmd: Commands,
-    mut targets: ResMut<RadarTargets>,
+    mut tracks: ResMut<RadarTracks>,
+    mut rng: ResMut<SiegeRng>,
+    clock: Res<MatchClock>,
+    time: Res<Time>,
 ) {
-    let collider_dim = Vec3::new(50., 200., 50.);
-    for t  in &ant_q {
-        // gizmos.ray(t.translation()  +  t.forward() * 5., t.forward() * 100., Color::hsl(10., 1., 0.5));
-
-        if let Some(shd) = spatial.cast_shape(
-            &Collider::cuboid(collider_dim.x, collider_dim.y, collider_dim.z), 
-            t.translation() + t.forward() * 15., 
-            Quat::IDENTITY, 
-            t.forward(), 
-            &ShapeCastConfig {
-                max_distance: 160.,
-                ignore_origin_penetration: true,
-                ..default()
-            },
-            &SpatialQueryFilter::from_mask(GameLayer::Attacker)
-        ) {
-            if targets.0.iter().find(|el| {
-                **el == shd.entity
-            }).is_none() {
-                cmd.trigger(SetTarget(shd.entity));
-                targets.0.push(shd.entity);
+    for (t, antenna_of) in &ant_q {
+        let Ok((sensor, radar_t, sweep)) = sensor_q.get(antenna_of.0) else {
+            continue;
+        };
+        let forward = (radar_t.rotation() * Quat::from_rotation_y(sweep.0) * Vec3::NEG_Z).with_y(0.).normalize_or_zero();
+        for (target_e, target_t) in &target_q {
+            let offset = target_t.translation() - t.translation();
+            let range = offset.length();
+            if range > sensor.max_range || range < f32::EPSILON {
+                continue;
+            }
+            let flat = offset.with_y(0.);
+            let bearing = flat.normalize_or_zero().dot(forward).clamp(-1., 1.).acos();
+            let elevation = offset.y.atan2(flat.length());
+            if bearing > sensor.beam_width * 0.5 || elevation.abs() > sensor.elevation {
+                continue;
+            }
+            if rng.f32() >= sensor.step_probability(range, time.delta_secs()) {
+                continue;
             }
+            let reported = target_t.translation() + sensor.noise(range, &mut rng);
+
+            let track = match tracks.0.iter().position(|tr| tr.entity == target_e) {
+                Some(idx) => &mut tracks.0[idx],
+                None => {
+                    cmd.trigger(SetTarget(target_e));
+                    tracks.0.push(Track::new(target_e));
+                    tracks.0.last_mut().unwrap()
+                }
+            };
+            if !track.seen_by.contains(&antenna_of.0) {
+                track.seen_by.push(antenna_of.0);
+            }
+            if track.last_seen() < clock.elapsed || track.history.is_empty() {
+                if track.history.len() == TRACK_HISTORY {
+                    track.history.pop_front();
+                }
+                track.history.push_back((clock.elapsed, reported));
+            }
+        }
+    }
+}
+
+// ---
+
+/// Holds the radar clip still at the frame matching `Sweep`, so the model shows where the beam
+/// is. The clip is taken to be one full turn of the antenna, starting at the radar's facing.
+fn sync_sweep(
+    radar_q: Query<(&Sweep, &CurrentAnimation, &AnimationKey), With<Radar>>,
+    mut player_q: Query<&mut AnimationPlayer>,
+    all_animations: Res<AllAnimations>,
+    graphs: Res<Assets<AnimationGraph>>,
+    clips: Res<Assets<AnimationClip>>,
+) {
+    for (sweep, ca, key) in &radar_q {
+        let Some(ani_set) = all_animations.0.get(key) else {
+            continue;
+        };
+        let node = ani_set.animations[ca.0];
+        let duration = graphs.get(&ani_set.graph)
+            .and_then(|g| g.get(node))
+            .and_then(|n| match &n.node_type {
+                AnimationNodeType::Clip(clip) => clips.get(clip),
+                _ => None,
+            })
+            .map(|clip| clip.duration());
+        let (Some(duration), Ok(mut player)) = (duration, player_q.get_mut(ca.1)) else {
+            continue;
+        };
+        if let Some(active) = player.animation_mut(node) {
+            active
+            .set_speed(0.)
+            .seek_to(sweep.0 / std::f32::consts::TAU * duration);
+        }
+    }
+}
+
+// ---
+
+/// Refreshes every track's estimates, drops stale ones and sorts by threat.
+fn update_tracks(
+    mut tracks: ResMut<RadarTracks>,
+    key_q: Query<&ProjectleKey>,
+    registry: Res<ProjectleRegistry>,
+    ftp: Res<FortressPosition>,
+    gravity: Res<Gravity>,
+    clock: Res<MatchClock>,
+) {
+    let now = clock.elapsed;
+    tracks.0.retain(|t| now - t.last_seen() < TRACK_TIMEOUT);
+
+    for track in &mut tracks.0 {
+        let (Some(&(t0, p0)), Some(&(t1, p1))) = (track.history.front(), track.history.back()) else {
+            continue;
+        };
+        if t1 > t0 {
+            // mean velocity over the history is the velocity at its midpoint
+            track.velocity = (p1 - p0) / (t1 - t0) + gravity.0 * (t1 - t0) * 0.5;
         }
+
+        let Some(pos) = track.position_at(now, gravity.0) else {
+            continue;
+        };
+        let vel = track.velocity + gravity.0 * (now - t1);
+        track.time_to_impact = time_to_ground(pos, vel, gravity.0.y);
+        track.impact = track.time_to_impact.map(|t| pos + vel * t + 0.5 * gravity.0 * t * t);
+
+        let mass = key_q.get(track.entity).ok()
+            .and_then(|k| registry.get(k))
+            .map_or(1., |def| def.mass());
+        let (damage, threat) = match (track.impact, track.time_to_impact) {
+            (Some(impact), Some(tti)) => {
+                let landing_speed = (vel + gravity.0 * tti).length();
+                let proximity = THREAT_FALLOFF / (THREAT_FALLOFF + impact.with_y(0.).distance(ftp.0.with_y(0.)));
+                let damage = mass * landing_speed * proximity;
+                (damage, damage / (1. + tti))
+            },
+            _ => (0., 0.),
+        };
+        track.predicted_damage = damage;
+        track.threat = threat;
     }
+
+    tracks.0.sort_by(|a, b| b.threat.total_cmp(&a.threat));
+}
+
+/// Seconds until a body at `pos` moving at `vel` drops to the ground (y = 0).
+fn time_to_ground(pos: Vec3, vel: Vec3, gravity_y: f32) -> Option<f32> {
+    let a = 0.5 * gravity_y;
+    if a.abs() < f32::EPSILON {
+        return (vel.y < 0.).then(|| -pos.y / vel.y);
+    }
+    let disc = vel.y * vel.y - 4. * a * pos.y;
+    if disc < 0. {
+        return None;
+    }
+    let sq = disc.sqrt();
+    [(-vel.y - sq) / (2. * a), (-vel.y + sq) / (2. * a)]
+        .into_iter()
+        .filter(|t| *t >= 0.)
+        .reduce(f32::max)
 }
 
 // ---
 
 fn targetable_despawn(
     tr: Trigger<OnRemove, Targetable>,
-    mut targets: ResMut<RadarTargets>,
+    mut tracks: ResMut<RadarTracks>,
 ) {
-    if let Some(idx) = targets.0.iter().position(|e| {
-        *e == tr.entity()
-    }) {
-        targets.0.swap_remove(idx);
-    }
+    tracks.0.retain(|t| t.entity != tr.entity());
 }
 
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G: f32 = -9.81;
+
+    #[test]
+    fn time_to_ground_from_rest() {
+        let t = time_to_ground(Vec3::new(0., 19.62, 0.), Vec3::ZERO, G).unwrap();
+        assert!((t - 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn time_to_ground_takes_the_landing_root() {
+        // leaves the ground going up: lands again, it doesn't count as already down
+        let t = time_to_ground(Vec3::ZERO, Vec3::new(30., 9.81, 0.), G).unwrap();
+        assert!((t - 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn time_to_ground_without_gravity() {
+        assert_eq!(time_to_ground(Vec3::new(0., 10., 0.), Vec3::new(0., -5., 0.), 0.), Some(2.));
+        assert_eq!(time_to_ground(Vec3::new(0., 10., 0.), Vec3::new(0., 5., 0.), 0.), None);
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::trebuchet_config::TrebuchetConfig;
+
+// ---
+
+/// How far a trebuchet throws for a given counterweight, sling and release point.
+#[derive(Resource, Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct RangeTable {
+    pub entries: Vec<RangeEntry>,
+}
+
+#[derive(Resource)]
+pub struct RangeTableHandle(pub Handle<RangeTable>);
+
+pub const RANGE_TABLE_PATH: &str = "configs/trebuchet.range.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RangeEntry {
+    pub cw_density: f32,
+    pub sling_len: f32,
+    pub unhooking_dot: f32,
+    /// Horizontal distance from the trebuchet to the landing point.
+    pub range: f32,
+    /// Sideways drift of the landing point, positive to the trebuchet's right.
+    pub drift: f32,
+}
+
+impl Default for RangeTable {
+    fn default() -> Self {
+        // rough figures for the default config, replace with a calibrated table
+        let entry = |unhooking_dot, range| RangeEntry {
+            cw_density: 9.5,
+            sling_len: 11.25,
+            unhooking_dot,
+            range,
+            drift: 0.,
+        };
+        Self {
+            entries: vec![entry(0.9, 90.), entry(0.95, 130.), entry(0.99, 160.)],
+        }
+    }
+}
+
+impl RangeTable {
+    /// Release dot expected to throw `range` metres with `cfg`'s counterweight and sling,
+    /// interpolated over the entries of the closest calibrated pair.
+    pub fn release_for(&self, cfg: &TrebuchetConfig, range: f32) -> Option<f32> {
+        let mut row = self.row_for(cfg);
+        row.sort_by(|a, b| a.range.total_cmp(&b.range));
+        let first = row.first()?;
+        let last = row.last()?;
+        if range <= first.range {
+            return Some(first.unhooking_dot);
+        }
+        if range >= last.range {
+            return Some(last.unhooking_dot);
+        }
+        row.windows(2).find(|w| range >= w[0].range && range <= w[1].range).map(|w| {
+            let k = (range - w[0].range) / (w[1].range - w[0].range).max(f32::EPSILON);
+            w[0].unhooking_dot + (w[1].unhooking_dot - w[0].unhooking_dot) * k
+        })
+    }
+
+    /// Entries sharing the (cw_density, sling_len) pair closest to `cfg`.
+    fn row_for(&self, cfg: &TrebuchetConfig) -> Vec<RangeEntry> {
+        let distance = |e: &RangeEntry| {
+            ((e.cw_density - cfg.cw_density) / cfg.cw_density.max(f32::EPSILON)).powi(2) +
+            ((e.sling_len - cfg.sling_len) / cfg.sling_len.max(f32::EPSILON)).powi(2)
+        };
+        let Some(closest) = self.entries.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))) else {
+            return Vec::new();
+        };
+        self.entries.iter()
+            .filter(|e| e.cw_density == closest.cw_density && e.sling_len == closest.sling_len)
+            .copied()
+            .collect()
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cw_density: f32, unhooking_dot: f32, range: f32) -> RangeEntry {
+        RangeEntry { cw_density, sling_len: 11.25, unhooking_dot, range, drift: 0. }
+    }
+
+    #[test]
+    fn release_for_interpolates_between_entries() {
+        let dot = RangeTable::default().release_for(&TrebuchetConfig::default(), 110.).unwrap();
+        assert!((dot - 0.925).abs() < 1e-5);
+    }
+
+    #[test]
+    fn release_for_clamps_to_the_calibrated_ranges() {
+        let table = RangeTable::default();
+        let cfg = TrebuchetConfig::default();
+        assert_eq!(table.release_for(&cfg, 10.), Some(0.9));
+        assert_eq!(table.release_for(&cfg, 500.), Some(0.99));
+    }
+
+    #[test]
+    fn release_for_uses_the_closest_row() {
+        let table = RangeTable {
+            entries: vec![entry(7.5, 0.9, 60.), entry(7.5, 0.99, 100.), entry(11.5, 0.9, 120.), entry(11.5, 0.99, 200.)],
+        };
+        let cfg = TrebuchetConfig { cw_density: 8., ..default() };
+        assert_eq!(table.release_for(&cfg, 100.), Some(0.99));
+        let cfg = TrebuchetConfig { cw_density: 11., ..default() };
+        assert_eq!(table.release_for(&cfg, 100.), Some(0.9));
+    }
+
+    #[test]
+    fn release_for_needs_entries() {
+        let table = RangeTable { entries: Vec::new() };
+        assert_eq!(table.release_for(&TrebuchetConfig::default(), 100.), None);
+    }
+}
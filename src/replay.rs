@@ -0,0 +1,564 @@
+use std::{collections::HashMap, fs::File, io::{BufReader, BufWriter}, path::{Path, PathBuf}};
+
+use avian3d::prelude::*;
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::camera::CameraPlugin;
+use crate::env::EnvPlugin;
+use crate::projectle::ProjectleSpawn;
+use crate::rng::SiegeRng;
+use crate::shared::SetTarget;
+use crate::trebuchet::{StateArming, StateIdle, StateLoose, StateTension, Trebuchet};
+use crate::GameState;
+
+// ---
+
+/// Everything needed to watch a siege again: what every rigid body looked like and where it
+/// was on each fixed step, plus the gameplay events worth marking on the timeline.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Replay {
+    pub seed: u64,
+    /// Seconds per frame, the fixed timestep of the recorded run.
+    pub step: f32,
+    pub bodies: Vec<ReplayBody>,
+    /// One entry per fixed step, holding only the bodies that moved during it.
+    pub frames: Vec<Vec<BodyPose>>,
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReplayBody {
+    pub name: String,
+    pub shape: ReplayShape,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum ReplayShape {
+    Sphere(f32),
+    /// Half extents of the collider's bounds.
+    Cuboid(Vec3),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct BodyPose {
+    pub body: u32,
+    pub pos: Vec3,
+    pub rot: Quat,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayEvent {
+    pub frame: u32,
+    pub kind: ReplayEventKind,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum ReplayEventKind {
+    ProjectleSpawn { key: String, pos: Vec3 },
+    SetTarget { body: Option<u32> },
+    Trebuchet { pos: Vec3, phase: TrebuchetPhase },
+    Despawn { body: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrebuchetPhase {
+    Idle,
+    Tension,
+    Arming,
+    Loose,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayError {
+    #[error("could not open replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not encode replay: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("replay refers to body {0}, but only {1} were recorded")]
+    UnknownBody(u32, usize),
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Ok(bincode::deserialize_from(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        Ok(bincode::serialize_into(BufWriter::new(File::create(path)?), self)?)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 * self.step
+    }
+}
+
+/// Value following `flag` on the command line.
+fn path_arg(flag: &str) -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+}
+
+// --- recording
+
+/// Records the running siege and writes it to `path` when the app exits. Recording stops once
+/// the match reaches Results.
+pub struct ReplayRecordPlugin {
+    pub path: PathBuf,
+}
+
+impl ReplayRecordPlugin {
+    /// From `--record <path>`.
+    pub fn from_args() -> Option<Self> {
+        path_arg("--record").map(|path| Self { path })
+    }
+}
+
+impl Plugin for ReplayRecordPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(ReplayRecorder {
+            path: self.path.clone(),
+            replay: Replay::default(),
+            ids: HashMap::new(),
+        })
+        .add_systems(Startup, start_recording)
+        .add_systems(FixedLast, record_frame.run_if(not(in_state(GameState::Loading))).run_if(not(in_state(GameState::Results))))
+        .add_systems(Last, save_recording.run_if(on_event::<AppExit>))
+        .add_observer(record_projectle)
+        .add_observer(record_target)
+        .add_observer(record_phase::<StateIdle>)
+        .add_observer(record_phase::<StateTension>)
+        .add_observer(record_phase::<StateArming>)
+        .add_observer(record_phase::<StateLoose>)
+        ;
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    replay: Replay,
+    ids: HashMap<Entity, u32>,
+}
+
+impl ReplayRecorder {
+    fn push(&mut self, kind: ReplayEventKind) {
+        let frame = self.replay.frames.len() as u32;
+        self.replay.events.push(ReplayEvent { frame, kind });
+    }
+}
+
+trait Phase: Component {
+    const PHASE: TrebuchetPhase;
+}
+
+impl Phase for StateIdle { const PHASE: TrebuchetPhase = TrebuchetPhase::Idle; }
+impl Phase for StateTension { const PHASE: TrebuchetPhase = TrebuchetPhase::Tension; }
+impl Phase for StateArming { const PHASE: TrebuchetPhase = TrebuchetPhase::Arming; }
+impl Phase for StateLoose { const PHASE: TrebuchetPhase = TrebuchetPhase::Loose; }
+
+// ---
+
+fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    rng: Res<SiegeRng>,
+    fixed: Res<Time<Fixed>>,
+) {
+    recorder.replay.seed = rng.seed();
+    recorder.replay.step = fixed.timestep().as_secs_f32();
+    info!("Recording replay to {}", recorder.path.display());
+}
+
+// ---
+
+fn record_frame(
+    mut recorder: ResMut<ReplayRecorder>,
+    body_q: Query<
+        (Entity, &Position, &Rotation, &Collider, Option<&Name>),
+        (With<RigidBody>, Without<RigidBodyDisabled>, Or<(Changed<Position>, Changed<Rotation>)>)
+    >,
+    mut removed: RemovedComponents<RigidBody>,
+    // pooled projectiles are disabled, not despawned, and come back as new bodies
+    disabled_q: Query<Entity, Added<RigidBodyDisabled>>,
+) {
+    let recorder = recorder.as_mut();
+    for e in removed.read().chain(&disabled_q) {
+        if let Some(body) = recorder.ids.remove(&e) {
+            recorder.push(ReplayEventKind::Despawn { body });
+        }
+    }
+
+    let mut frame = Vec::new();
+    for (e, pos, rot, collider, name) in &body_q {
+        let body = match recorder.ids.get(&e) {
+            Some(&body) => body,
+            None => {
+                // infinite colliders (the stand-in ground) have nothing to draw
+                let Some(shape) = shape_of(collider) else {
+                    continue;
+                };
+                let body = recorder.replay.bodies.len() as u32;
+                recorder.replay.bodies.push(ReplayBody {
+                    name: name.map(|n| n.to_string()).unwrap_or_default(),
+                    shape,
+                });
+                recorder.ids.insert(e, body);
+                body
+            }
+        };
+        frame.push(BodyPose { body, pos: pos.0, rot: rot.0 });
+    }
+    recorder.replay.frames.push(frame);
+}
+
+fn shape_of(collider: &Collider) -> Option<ReplayShape> {
+    if let Some(ball) = collider.shape_scaled().as_ball() {
+        return Some(ReplayShape::Sphere(ball.radius));
+    }
+    let aabb = collider.aabb(Vec3::ZERO, Quat::IDENTITY);
+    let half = (aabb.max - aabb.min) * 0.5;
+    half.is_finite().then_some(ReplayShape::Cuboid(half))
+}
+
+// ---
+
+fn record_projectle(
+    tr: Trigger<ProjectleSpawn>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let event = tr.event();
+    recorder.push(ReplayEventKind::ProjectleSpawn { key: event.key.0.to_string(), pos: event.pos });
+}
+
+// ---
+
+fn record_target(
+    tr: Trigger<SetTarget>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let body = recorder.ids.get(&tr.event().0).copied();
+    recorder.push(ReplayEventKind::SetTarget { body });
+}
+
+// ---
+
+fn record_phase<P: Phase>(
+    tr: Trigger<OnAdd, P>,
+    treb_q: Query<&GlobalTransform, With<Trebuchet>>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let Ok(gt) = treb_q.get(tr.entity()) else {
+        return;
+    };
+    recorder.push(ReplayEventKind::Trebuchet { pos: gt.translation(), phase: P::PHASE });
+}
+
+// ---
+
+fn save_recording(
+    recorder: Res<ReplayRecorder>,
+) {
+    match recorder.replay.save(&recorder.path) {
+        Ok(()) => info!(
+            "Replay of {:.1}s, {} bodies written to {}",
+            recorder.replay.duration(), recorder.replay.bodies.len(), recorder.path.display()
+        ),
+        Err(e) => error!("Could not write {}: {e}", recorder.path.display()),
+    }
+}
+
+// --- playback
+
+/// Plays a recorded siege back with proxy shapes instead of the simulation.
+/// Space pauses, Left/Right seek, Up/Down change the speed, Home restarts.
+pub struct ReplayPlayerPlugin {
+    pub path: PathBuf,
+}
+
+impl ReplayPlayerPlugin {
+    /// From `--replay <path>`.
+    pub fn from_args() -> Option<Self> {
+        path_arg("--replay").map(|path| Self { path })
+    }
+}
+
+impl Plugin for ReplayPlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins((CameraPlugin, EnvPlugin))
+        .init_state::<GameState>()
+        .insert_resource(ReplayPath(self.path.clone()))
+        .init_resource::<ReplayCursor>()
+        .add_systems(Startup, load)
+        .add_systems(OnEnter(GameState::Replay), spawn_proxies)
+        .add_systems(Update, (controls, advance, pose, mark).chain().run_if(in_state(GameState::Replay)))
+        ;
+    }
+}
+
+#[derive(Resource)]
+struct ReplayPath(PathBuf);
+
+/// Loaded replay with each body's poses split into its own timeline for seeking.
+#[derive(Resource)]
+pub struct ReplayTimeline {
+    pub replay: Replay,
+    /// Per body: (frame, position, rotation), in frame order.
+    tracks: Vec<Vec<(u32, Vec3, Quat)>>,
+    /// Per body: frame it was removed on.
+    despawned: Vec<Option<u32>>,
+}
+
+impl ReplayTimeline {
+    /// Fails on body numbers outside the recorded bodies, as in a truncated file or one
+    /// written by a different build.
+    pub fn new(replay: Replay) -> Result<Self, ReplayError> {
+        let count = replay.bodies.len();
+        let check = |body: u32| if (body as usize) < count {Ok(body as usize)} else {Err(ReplayError::UnknownBody(body, count))};
+
+        let mut tracks = vec![Vec::new(); count];
+        for (frame, poses) in replay.frames.iter().enumerate() {
+            for p in poses {
+                tracks[check(p.body)?].push((frame as u32, p.pos, p.rot));
+            }
+        }
+        let mut despawned = vec![None; count];
+        for event in &replay.events {
+            match event.kind {
+                ReplayEventKind::Despawn { body } => despawned[check(body)?] = Some(event.frame),
+                ReplayEventKind::SetTarget { body: Some(body) } => { check(body)?; },
+                _ => {}
+            }
+        }
+        Ok(Self { replay, tracks, despawned })
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayCursor {
+    /// Seconds into the replay.
+    pub at: f32,
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for ReplayCursor {
+    fn default() -> Self {
+        Self { at: 0., speed: 1., paused: false }
+    }
+}
+
+#[derive(Component)]
+struct Proxy(u32);
+
+const SEEK_SECS: f32 = 5.;
+const MIN_SPEED: f32 = 0.125;
+const MAX_SPEED: f32 = 8.;
+/// Seconds an event stays marked after its frame.
+const MARK_SECS: f32 = 1.5;
+
+// ---
+
+fn load(
+    path: Res<ReplayPath>,
+    mut cmd: Commands,
+    mut next: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let timeline = match Replay::load(&path.0).and_then(ReplayTimeline::new) {
+        Ok(t) => t,
+        Err(e) => {
+            // there's nothing else to show, so don't sit in Loading
+            error!("Could not read {}: {e}", path.0.display());
+            exit.send(AppExit::error());
+            return;
+        }
+    };
+
+    info!("Replay of {:.1}s, seed {}", timeline.replay.duration(), timeline.replay.seed);
+    cmd.insert_resource(timeline);
+    next.set(GameState::Replay);
+}
+
+// ---
+
+fn spawn_proxies(
+    timeline: Res<ReplayTimeline>,
+    mut cmd: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    cmd.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(1000., 1000.))),
+        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.25, 0.2))),
+        Name::new("Field"),
+    ));
+
+    let ball = materials.add(Color::hsl(150., 1.0, 0.5));
+    let bullet = materials.add(StandardMaterial {
+        emissive: LinearRgba::from(Color::hsl(47., 1.0, 0.5)),
+        ..default()
+    });
+    let other = materials.add(Color::srgb(0.6, 0.6, 0.6));
+
+    for (i, body) in timeline.replay.bodies.iter().enumerate() {
+        let mesh = match body.shape {
+            ReplayShape::Sphere(r) => meshes.add(Sphere::new(r)),
+            ReplayShape::Cuboid(half) => meshes.add(Cuboid::from_size(half * 2.)),
+        };
+        let material = match body.name.as_str() {
+            "Ball" => ball.clone(),
+            "Bullet" => bullet.clone(),
+            _ => other.clone(),
+        };
+        cmd.spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(material),
+            Transform::default(),
+            Visibility::Hidden,
+            Proxy(i as u32),
+        ));
+    }
+}
+
+// ---
+
+fn controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<ReplayCursor>,
+    timeline: Res<ReplayTimeline>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        cursor.paused = !cursor.paused;
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        cursor.at += SEEK_SECS;
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        cursor.at -= SEEK_SECS;
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        cursor.speed = (cursor.speed * 2.).min(MAX_SPEED);
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        cursor.speed = (cursor.speed * 0.5).max(MIN_SPEED);
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        cursor.at = 0.;
+    }
+    cursor.at = cursor.at.clamp(0., timeline.replay.duration());
+}
+
+// ---
+
+fn advance(
+    mut cursor: ResMut<ReplayCursor>,
+    timeline: Res<ReplayTimeline>,
+    time: Res<Time>,
+) {
+    if !cursor.paused {
+        cursor.at = (cursor.at + time.delta_secs() * cursor.speed).min(timeline.replay.duration());
+    }
+}
+
+// ---
+
+fn pose(
+    cursor: Res<ReplayCursor>,
+    timeline: Res<ReplayTimeline>,
+    mut proxy_q: Query<(&Proxy, &mut Transform, &mut Visibility)>,
+) {
+    let frame = (cursor.at / timeline.replay.step.max(f32::EPSILON)) as u32;
+    for (proxy, mut t, mut vis) in &mut proxy_q {
+        let track = &timeline.tracks[proxy.0 as usize];
+        // last pose at or before the cursor
+        let idx = track.partition_point(|(f, ..)| *f <= frame);
+        let gone = timeline.despawned[proxy.0 as usize].is_some_and(|f| f <= frame);
+        if idx == 0 || gone {
+            *vis = Visibility::Hidden;
+            continue;
+        }
+        let (_, pos, rot) = track[idx - 1];
+        *t = Transform::from_translation(pos).with_rotation(rot);
+        *vis = Visibility::Inherited;
+    }
+}
+
+// ---
+
+fn mark(
+    cursor: Res<ReplayCursor>,
+    timeline: Res<ReplayTimeline>,
+    proxy_q: Query<(&Proxy, &Transform)>,
+    mut gizmos: Gizmos,
+) {
+    let step = timeline.replay.step.max(f32::EPSILON);
+    let to = (cursor.at / step) as u32;
+    let from = ((cursor.at - MARK_SECS).max(0.) / step) as u32;
+    for event in timeline.replay.events.iter().filter(|e| e.frame >= from && e.frame <= to) {
+        match &event.kind {
+            ReplayEventKind::ProjectleSpawn { pos, .. } => {
+                gizmos.sphere(Isometry3d::from_translation(*pos), 1., Color::WHITE);
+            },
+            ReplayEventKind::SetTarget { body: Some(body) } => {
+                if let Some((_, t)) = proxy_q.iter().find(|(p, _)| p.0 == *body) {
+                    gizmos.sphere(Isometry3d::from_translation(t.translation), 3., Color::srgb(1., 0.2, 0.2));
+                }
+            },
+            ReplayEventKind::Trebuchet { pos, phase } => {
+                let color = match phase {
+                    TrebuchetPhase::Idle => Color::srgb(0.5, 0.5, 0.5),
+                    TrebuchetPhase::Tension => Color::srgb(1., 0.8, 0.),
+                    TrebuchetPhase::Arming => Color::srgb(1., 0.4, 0.),
+                    TrebuchetPhase::Loose => Color::srgb(1., 0., 0.),
+                };
+                gizmos.circle(Isometry3d::new(*pos + Vec3::Y * 0.2, Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)), 6., color);
+            },
+            _ => {}
+        }
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(frames: Vec<Vec<u32>>, events: Vec<ReplayEvent>) -> Replay {
+        let body = |name: &str| ReplayBody { name: name.into(), shape: ReplayShape::Sphere(1.) };
+        Replay {
+            seed: 0,
+            step: 1. / 64.,
+            bodies: vec![body("ball"), body("bullet")],
+            frames: frames.into_iter()
+                .map(|f| f.into_iter().map(|body| BodyPose { body, pos: Vec3::ZERO, rot: Quat::IDENTITY }).collect())
+                .collect(),
+            events,
+        }
+    }
+
+    #[test]
+    fn timeline_splits_poses_per_body() {
+        let events = vec![ReplayEvent { frame: 1, kind: ReplayEventKind::Despawn { body: 1 } }];
+        let timeline = ReplayTimeline::new(replay(vec![vec![0], vec![0, 1]], events)).unwrap();
+        let frames = |body: usize| timeline.tracks[body].iter().map(|(f, ..)| *f).collect::<Vec<_>>();
+        assert_eq!(frames(0), vec![0, 1]);
+        assert_eq!(frames(1), vec![1]);
+        assert_eq!(timeline.despawned, vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn timeline_rejects_unknown_bodies() {
+        let bad_pose = ReplayTimeline::new(replay(vec![vec![0, 2]], Vec::new()));
+        assert!(matches!(bad_pose, Err(ReplayError::UnknownBody(2, 2))));
+
+        let events = vec![ReplayEvent { frame: 0, kind: ReplayEventKind::SetTarget { body: Some(5) } }];
+        let bad_target = ReplayTimeline::new(replay(vec![vec![0]], events));
+        assert!(matches!(bad_target, Err(ReplayError::UnknownBody(5, 2))));
+    }
+}
@@ -0,0 +1,63 @@
+use std::ops::RangeBounds;
+
+use bevy::prelude::*;
+
+// ---
+
+/// The only source of randomness for gameplay: the same seed and configs replay the same siege.
+#[derive(Resource)]
+pub struct SiegeRng {
+    seed: u64,
+    rng: fastrand::Rng,
+}
+
+impl Default for SiegeRng {
+    fn default() -> Self {
+        Self::new(fastrand::u64(..))
+    }
+}
+
+impl SiegeRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    /// Seed from `--seed <u64>` on the command line, random otherwise.
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        args.iter()
+            .position(|a| a == "--seed")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|v| v.parse().ok())
+            .map_or_else(Self::default, Self::new)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn u64(&mut self, range: impl RangeBounds<u64>) -> u64 {
+        self.rng.u64(range)
+    }
+
+    /// A value from a `(from, to)` pair as written in configs: both ends included, either order.
+    pub fn between(&mut self, (from, to): (u64, u64)) -> u64 {
+        self.rng.u64(from.min(to)..=from.max(to))
+    }
+
+    pub fn f32(&mut self) -> f32 {
+        self.rng.f32()
+    }
+
+    /// Two independent standard normal samples (Box-Muller).
+    pub fn gaussian_pair(&mut self) -> (f32, f32) {
+        let u1 = self.rng.f32().max(f32::MIN_POSITIVE);
+        let u2 = self.rng.f32();
+        let r = (-2. * u1.ln()).sqrt();
+        let theta = std::f32::consts::TAU * u2;
+        (r * theta.cos(), r * theta.sin())
+    }
+}
@@ -10,6 +10,7 @@ pub enum GameLayer {
 }
 
 
+/// A radar picked up a new contact and opened a track on it.
 #[derive(Event)]
 pub struct SetTarget (pub Entity);
 
@@ -19,6 +20,18 @@ pub struct  Targetable;
 #[derive(Component)]
 pub struct Interval(pub Timer);
 
+/// Shots left for an attacking unit; units without it never run dry.
+#[derive(Component)]
+pub struct Ammo(pub u32);
+
+/// Present when something other than the default layouts decides which units to spawn.
+#[derive(Resource)]
+pub struct ScriptedDeployment;
+
+/// Present when running without a window: plugins spawn stand-in colliders instead of glTF scenes.
+#[derive(Resource)]
+pub struct Headless;
+
 
     
 
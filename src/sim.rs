@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use bevy::{
+    app::{AppExit, PluginGroupBuilder, ScheduleRunnerPlugin},
+    asset::AssetPlugin,
+    hierarchy::HierarchyPlugin,
+    prelude::*,
+    scene::ScenePlugin,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+};
+use avian3d::PhysicsPlugins;
+
+use crate::shared::Headless;
+use crate::{animator::AnimatorPlugin, camera::CameraPlugin, env::EnvPlugin, GameState, SiegePlugins};
+
+// ---
+
+/// Engine side of a windowless run: `MinimalPlugins`, assets, states, transforms, scenes and
+/// avian physics, with time advancing a fixed step per frame however long the frame really took.
+/// Logging is left to the app, which may already have a subscriber set up.
+pub struct HeadlessPlugin;
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            AssetPlugin::default(),
+            StatesPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            // avian's collider constructors look for scene instances
+            ScenePlugin,
+            PhysicsPlugins::default(),
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .insert_resource(Headless)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(SIM_STEP)))
+        ;
+    }
+}
+
+/// `SiegePlugins` without the members that only make sense on screen.
+pub fn headless_siege() -> PluginGroupBuilder {
+    SiegePlugins
+    .build()
+    .disable::<CameraPlugin>()
+    .disable::<EnvPlugin>()
+    .disable::<AnimatorPlugin>()
+}
+
+// ---
+
+/// Runs a siege without a window or GPU, with stand-in colliders in place of the glTF scenes.
+pub struct SiegeSimPlugin;
+impl Plugin for SiegeSimPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins((HeadlessPlugin, headless_siege()))
+        .init_resource::<SimSettings>()
+        .add_systems(Update, finish)
+        .add_systems(OnEnter(GameState::Results), exit)
+        ;
+    }
+}
+
+// ---
+
+/// Simulated seconds advanced per frame, independent of wall-clock time.
+/// Matches the default `Time<Fixed>` rate, so every frame runs exactly one fixed step.
+const SIM_STEP: f64 = 1. / 64.;
+
+#[derive(Resource)]
+pub struct SimSettings {
+    /// Simulated seconds before the app exits, unless the match reaches Results first.
+    pub duration: f32,
+}
+
+impl Default for SimSettings {
+    fn default() -> Self {
+        Self { duration: 120. }
+    }
+}
+
+impl SimSettings {
+    /// Reads `--duration <seconds>` from the command line.
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let mut settings = Self::default();
+        if let Some(d) = args.iter()
+            .position(|a| a == "--duration")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|v| v.parse().ok())
+        {
+            settings.duration = d;
+        }
+        settings
+    }
+}
+
+// ---
+
+fn finish(
+    time: Res<Time>,
+    settings: Res<SimSettings>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if time.elapsed_secs() >= settings.duration {
+        info!("Siege simulated for {:.1}s", time.elapsed_secs());
+        exit.send(AppExit::Success);
+    }
+}
+
+// ---
+
+fn exit(
+    mut exit: EventWriter<AppExit>,
+) {
+    exit.send(AppExit::Success);
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use avian3d::prelude::Position;
+
+    use super::*;
+    use crate::{fortress::FortressIntegrity, hud::SiegeStats, projectle::Ball, rng::SiegeRng};
+
+    /// What a siege looks like after a while: fortress damage, the counts and every ball.
+    #[derive(PartialEq, Debug)]
+    struct Outcome {
+        destroyed: f32,
+        counts: [u32; 4],
+        balls: Vec<Vec3>,
+    }
+
+    /// Runs a headless siege from `seed` for `steps` fixed steps past loading.
+    fn siege(seed: u64, steps: usize) -> Outcome {
+        let mut app = App::new();
+        app
+        .insert_resource(SiegeRng::new(seed))
+        .add_plugins((HeadlessPlugin, headless_siege()))
+        ;
+        app.finish();
+        app.cleanup();
+        for _ in 0..10_000 {
+            if *app.world().resource::<State<GameState>>() != GameState::Loading {
+                break;
+            }
+            app.update();
+        }
+        assert_ne!(*app.world().resource::<State<GameState>>(), GameState::Loading, "assets never loaded");
+        for _ in 0..steps {
+            app.update();
+        }
+
+        let world = app.world_mut();
+        let stats = world.resource::<SiegeStats>();
+        let counts = [stats.balls_thrown, stats.balls_intercepted, stats.rounds_fired, stats.rounds_hit];
+        Outcome {
+            destroyed: world.resource::<FortressIntegrity>().destroyed(),
+            counts,
+            balls: world.query_filtered::<&Position, With<Ball>>().iter(world).map(|p| p.0).collect(),
+        }
+    }
+
+    #[test]
+    fn same_seed_same_siege() {
+        // countdown plus the opening throws and their flight
+        let steps = (30. / SIM_STEP) as usize;
+        let first = siege(1234, steps);
+        assert!(first.counts[0] > 0, "nothing was thrown");
+        assert_eq!(first, siege(1234, steps));
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::projectle::{Ball, BallHit, LifeTime, OnHit, ProjectleKey, ProjectleRegistry};
+use crate::shared::{GameLayer, Headless, Targetable};
+use crate::GameState;
+
+// ---
+
+/// Hitscan bullets: each tracer follows its ballistic path one fixed step at a time with a
+/// ray cast against the attackers' layer, so nothing tunnels and no rigid body is spawned.
+pub struct TracerPlugin;
+impl Plugin for TracerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(FixedUpdate, step.run_if(any_with_component::<Tracer>).run_if(in_state(GameState::Siege)))
+        .add_systems(Update, draw.run_if(any_with_component::<Tracer>).run_if(not(resource_exists::<Headless>)))
+        .add_systems(Update, drop_spent.after(draw).run_if(any_with_component::<Spent>))
+        ;
+    }
+}
+
+// ---
+
+#[derive(Component)]
+pub struct Tracer {
+    pub pos: Vec3,
+    pub vel: Vec3,
+    /// Where the tracer was a step ago, for drawing the streak.
+    pub prev: Vec3,
+    /// Projectile kind whose mass is used for the hit impulse.
+    pub key: ProjectleKey,
+    pub life: Timer,
+    /// The barrel that fired it.
+    pub owner: Option<Entity>,
+}
+
+impl Tracer {
+    pub fn new(pos: Vec3, vel: Vec3, key: ProjectleKey, lifetime: f32, owner: Option<Entity>) -> Self {
+        Self {
+            pos,
+            vel,
+            prev: pos,
+            key,
+            life: Timer::new(Duration::from_secs_f32(lifetime), TimerMode::Once),
+            owner,
+        }
+    }
+}
+
+/// The tracer has hit something; it stays for one frame so its last streak is drawn.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Spent;
+
+/// Seconds a tracer flies before it's dropped.
+pub const TRACER_LIFETIME: f32 = 2.;
+/// Length of the drawn streak in steps of travel.
+const STREAK: f32 = 3.;
+
+// ---
+
+fn step(
+    mut tracer_q: Query<(Entity, &mut Tracer), Without<Spent>>,
+    mut target_q: Query<(Option<&mut LifeTime>, Option<&OnHit>, Option<&mut ExternalImpulse>, Has<Ball>), With<Targetable>>,
+    collider_parent_q: Query<&ColliderParent>,
+    spatial: SpatialQuery,
+    registry: Res<ProjectleRegistry>,
+    gravity: Res<Gravity>,
+    time: Res<Time>,
+    mut cmd: Commands,
+) {
+    let dt = time.delta_secs();
+    let filter = SpatialQueryFilter::from_mask(GameLayer::Attacker);
+    for (e, mut tracer) in &mut tracer_q {
+        if tracer.life.tick(time.delta()).finished() {
+            cmd.entity(e).despawn();
+            continue;
+        }
+
+        let next = tracer.pos + tracer.vel * dt + 0.5 * gravity.0 * dt * dt;
+        let Ok((dir, len)) = Dir3::new_and_length(next - tracer.pos) else {
+            continue;
+        };
+        tracer.prev = tracer.pos;
+
+        let Some(hit) = spatial.cast_ray(tracer.pos, dir, len, true, &filter) else {
+            tracer.pos = next;
+            tracer.vel += gravity.0 * dt;
+            continue;
+        };
+
+        tracer.pos += dir * hit.distance;
+        cmd.entity(e).insert(Spent);
+
+        let body = collider_parent_q.get(hit.entity).map_or(hit.entity, |p| p.get());
+        let Ok((lifetime, on_hit, ext, ball)) = target_q.get_mut(body) else {
+            continue;
+        };
+        if ball {
+            cmd.trigger(BallHit { ball: body, by: tracer.owner });
+        }
+        let mass = registry.get(&tracer.key).map_or(0., |def| def.mass());
+        let push = tracer.vel * mass;
+        match ext {
+            Some(mut ext) => { ext.apply_impulse(push); },
+            None => { cmd.entity(body).insert(ExternalImpulse::new(push)); },
+        }
+        // same outcome a bullet sphere's contact has, see `projectle::despawn_on_collision`
+        if let (Some(mut lt), Some(OnHit::Expire)) = (lifetime, on_hit) {
+            lt.0.set_duration(Duration::ZERO);
+        }
+    }
+}
+
+// ---
+
+fn draw(
+    tracer_q: Query<&Tracer>,
+    mut gizmos: Gizmos,
+) {
+    for tracer in &tracer_q {
+        let tail = tracer.pos + (tracer.prev - tracer.pos) * STREAK;
+        gizmos.line(tail, tracer.pos, Color::srgb(1., 0.85, 0.3));
+    }
+}
+
+// ---
+
+fn drop_spent(
+    spent_q: Query<Entity, With<Spent>>,
+    mut cmd: Commands,
+) {
+    for e in &spent_q {
+        cmd.entity(e).despawn();
+    }
+}
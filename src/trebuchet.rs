@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{f32::consts::PI, time::Duration};
 
 use bevy::{
     // gizmos, 
@@ -8,22 +8,37 @@ use avian3d::prelude::*;
 use bevy::scene::SceneInstanceReady;
 
 use crate::{GameState, NotReady};
-use crate::shared::{Interval, Targetable};
-use crate::projectle::{Ball, LifeTime, ProjectleKey, ProjectleSpawn, Released, BALL_RADIUS};
+use crate::shared::{Ammo, Headless, Interval, Targetable};
+use crate::projectle::{Ball, LifeTime, ProjectleKey, ProjectleRegistry, ProjectleSpawn, Released, Thrown, BALL_RADIUS};
+use crate::rng::SiegeRng;
+use crate::range_table::{RangeTable, RangeTableHandle, RANGE_TABLE_PATH};
+use crate::ron_asset::RonAssetPlugin;
+use crate::trebuchet_config::{
+    DefaultTrebuchetConfig, TrebuchetConfig, TrebuchetConfigHandle, TrebuchetConfigOverrides, DEFAULT_CONFIG_PATH
+};
 pub struct TrebuchetPlugin;
 
 impl Plugin for TrebuchetPlugin {
     fn build(&self, app: &mut App) {
         app
+        .add_plugins(RonAssetPlugin::<TrebuchetConfig>::new(&["treb.ron"]))
+        .add_plugins(RonAssetPlugin::<RangeTable>::new(&["range.ron"]))
+        .init_resource::<TrebuchetConfigOverrides>()
+        .init_resource::<RangeTable>()
         .add_systems(Startup, startup)
-        .add_systems(OnEnter(GameState::Game), start_game)
-        .add_systems(Update, do_tension.run_if(any_with_component::<StateTension>))
-        .add_systems(Update, do_arming.run_if(on_event::<CollisionEnded>))
-        .add_systems(Update, do_loose.run_if(any_with_component::<StateLoose>))
-        .add_systems(Update, reload.run_if(any_with_component::<Interval>))
+        .add_systems(OnTransition { exited: GameState::Countdown, entered: GameState::Siege }, start_game)
+        .add_systems(Update, setup.run_if(any_with_component::<NotReady>))
+        .add_systems(Update, apply_config.run_if(on_event::<AssetEvent<TrebuchetConfig>>))
+        .add_systems(FixedUpdate, rerig.run_if(any_with_component::<Rerig>))
+        .add_systems(Update, apply_range_table.run_if(on_event::<AssetEvent<RangeTable>>))
+        .add_systems(FixedUpdate, do_tension.run_if(any_with_component::<StateTension>))
+        .add_systems(FixedUpdate, do_arming.run_if(on_event::<CollisionEnded>))
+        .add_systems(FixedUpdate, do_loose.run_if(any_with_component::<StateLoose>))
+        .add_systems(FixedUpdate, reload.run_if(any_with_component::<Interval>).run_if(in_state(GameState::Siege)))
+        .add_systems(FixedUpdate, aim.run_if(any_with_component::<TrebuchetTarget>))
+        .add_observer(spawn)
         .add_observer(enter_idle)
         .add_observer(enter_tension)
-        .add_observer(setup)
         .add_observer(enter_arming)
         ;
     }
@@ -67,66 +82,152 @@ pub struct StateLoose;
 #[derive(Component)]
 pub struct Link;
 
+#[derive(Event)]
+pub struct TrebuchetSpawn {
+    pub transform: Transform,
+    /// Falls back to `DEFAULT_CONFIG_PATH`.
+    pub config: Option<Handle<TrebuchetConfig>>,
+    /// Seconds into the siege of the first throw, a random reload interval if unset.
+    pub first_throw: Option<f32>,
+}
+
+/// Delay before the first throw, used up when the trebuchet first goes idle.
+#[derive(Component)]
+pub struct FirstThrow(pub f32);
+
+/// Under the player's control: the trebuchet only pulls when told to (`StateTension` from
+/// `StateIdle`), holds the arm down until it gets `ArmNow` and keeps the loaded sling
+/// swinging until it gets `ReleaseNow`.
+#[derive(Component)]
+pub struct Commanded;
+
+/// Keeps an idle trebuchet from pulling once its interval is up, see `attack_ai`.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct HoldFire;
+
+/// Lets a commanded trebuchet load the ball once its arm is down.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct ArmNow;
+
+/// Lets a commanded trebuchet's ball go on the next step, whatever the sling angle.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct ReleaseNow;
+
+/// The config changed: the sling and joints are rebuilt once the trebuchet isn't mid-throw.
+#[derive(Component)]
+pub struct Rerig;
+
+/// Point the trebuchet turns to and throws at.
+#[derive(Component)]
+pub struct TrebuchetTarget(pub Vec3);
+
+/// Release point for the next throw, overrides `TrebuchetConfig::unhooking_dot`.
+#[derive(Component, PartialEq)]
+pub struct ReleaseDot(pub f32);
+
 #[derive(Component)]
 pub struct Parts {
     pivot: Entity,
+    pivot_joint: Entity,
     se: Entity,
     arm: Entity,
     bar: Entity, 
     cw: Entity,
-    link: Option<Entity>
+    link: Option<Entity>,
+    /// Joints and sling pieces built from the config, see `rig`.
+    rig: Vec<Entity>,
 }
 
 impl Parts {
     fn new() -> Self{
         Self {pivot: Entity::PLACEHOLDER,
+            pivot_joint: Entity::PLACEHOLDER,
             se: Entity::PLACEHOLDER,
             arm: Entity::PLACEHOLDER,
             bar: Entity::PLACEHOLDER, 
             cw: Entity::PLACEHOLDER,
-            link: None
+            link: None,
+            rig: Vec::new(),
         }
     }
 }
 
 // -- CONSTANTS --
 
-const ARM_DIM: Vec3 =  Vec3::new(1., 1., 15.);
-const CW_DENSITY: f32 =  9.5;
-const PIVOT_DAMPING: f32 = 0.1; 
-const PIVOT_OFFSET: f32 = ARM_DIM.z  * 0.3; 
-const ARM_LONG_END_Y: f32 = 1.0;
-
-const SLING_ELEMENT_DENSITY: f32 = 100.;
-const SLING_ELEMENT_COUNT: u32 = 8;
-const SLING_LEN: f32 = ARM_DIM.z * 0.75;
-const UNHOOKING_DOT: f32 = 0.99;
+// Tunable parameters live in `TrebuchetConfig` (assets/configs/*.treb.ron)
 
 const TREBUCHET_DIM: Vec3 = Vec3::new(4., 8., 16.);  // ROUGLY
+/// Yaw speed (rad/s) while turning toward a `TrebuchetTarget`.
+const AIM_YAW_SPEED: f32 = 0.3;
+/// Yaw error (rad) below which the trebuchet counts as aimed.
+const AIM_TOLERANCE: f32 = 0.005;
 
 
 // ---
 
 fn startup(
     mut cmd: Commands,
-    assets: ResMut<AssetServer>
+    assets: ResMut<AssetServer>,
 ) {
-    let asset_handle = assets.load(GltfAssetLabel::Scene(0).from_asset("models/trebuchet.glb"));
-    let mut x = 0.;
-    for i in 0..11 {
-        x += 10. * i as f32 * (if i % 2 == 0 {1.} else {-1.});
-        cmd.spawn((
-            SceneRoot(asset_handle.clone()),
-            Transform::from_xyz(x, 0.1, 40.),
-            NotReady,
-            Trebuchet,
-            Name::new("Trebuchet"),
-            RigidBody::Static,
-        ))
+    cmd.insert_resource(DefaultTrebuchetConfig(assets.load(DEFAULT_CONFIG_PATH)));
+    cmd.insert_resource(RangeTableHandle(assets.load(RANGE_TABLE_PATH)));
+}
+
+// ---
+
+fn spawn(
+    tr: Trigger<TrebuchetSpawn>,
+    mut cmd: Commands,
+    assets: ResMut<AssetServer>,
+    headless: Option<Res<Headless>>,
+) {
+    let event = tr.event();
+    let config = event.config.clone().unwrap_or_else(|| assets.load(DEFAULT_CONFIG_PATH));
+    let treb_e = cmd.spawn((
+        event.transform,
+        NotReady,
+        Trebuchet,
+        TrebuchetConfigHandle(config),
+        Name::new("Trebuchet"),
+        RigidBody::Static,
+    ))
+    .id()
+    ;
+    if let Some(secs) = event.first_throw {
+        cmd.entity(treb_e).insert(FirstThrow(secs));
+    }
+    if headless.is_some() {
+        stand_in(&mut cmd, treb_e);
+    } else {
+        cmd.entity(treb_e)
+        .insert(SceneRoot(assets.load(GltfAssetLabel::Scene(0).from_asset("models/trebuchet.glb"))))
         .observe(explore)
         ;
-        // info!("Trebuchet spawned");
     }
+    // info!("Trebuchet spawned");
+}
+
+// ---
+
+fn stand_in(
+    cmd: &mut Commands,
+    treb_e: Entity,
+) {
+    // approximates the node layout of models/trebuchet.glb, the bar is turned so its
+    // far end (where the sling is pulled to) sits under the ball drop point
+    let mut parts = Parts::new();
+    parts.pivot = cmd.spawn((Pivot, Transform::from_xyz(0., 8., 0.))).id();
+    parts.arm = cmd.spawn((Arm, Transform::from_xyz(0., 8., 4.5))).id();
+    parts.cw = cmd.spawn((CounterWeight, Transform::from_xyz(0., 7., -3.))).id();
+    parts.bar = cmd.spawn((Bar, Transform::from_xyz(0., 0.25, -6.).with_rotation(Quat::from_rotation_y(PI)))).id();
+    let hill = cmd.spawn((Transform::from_xyz(0., 0.5, -14.), Collider::cuboid(2., 0.125, 8.))).id();
+    cmd.entity(treb_e)
+    .add_children(&[parts.pivot, parts.arm, parts.cw, parts.bar, hill])
+    .insert(parts)
+    ;
 }
 
 // ---
@@ -167,43 +268,78 @@ fn explore(
 // ---
 
 fn setup(
-    tr: Trigger<OnAdd, Parts>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials : ResMut<Assets<StandardMaterial>>,
-    mut parts_q: Query<&mut Parts, (Added<Parts>, With<Trebuchet>, With<NotReady>)>,
+    mut parts_q: Query<(Entity, &mut Parts, &TrebuchetConfigHandle), (With<Trebuchet>, With<NotReady>)>,
     arm_q: Query<&Transform, With<Arm>>,
+    configs: Res<Assets<TrebuchetConfig>>,
+    assets: Res<AssetServer>,
     mut cmd: Commands,
 ) {
-    let treb_e = tr.entity();
-    let mut parts = parts_q.get_mut(treb_e).unwrap(); 
-    cmd.entity(parts.arm)
-    .insert((
-        RigidBody::Dynamic,
-        MassPropertiesBundle::from_shape(&Collider::cuboid(ARM_DIM.x, ARM_DIM.y, ARM_DIM.z), 1.),
-    ));
-    let anchor_arm = Vec3::Z *  ARM_DIM.z * 0.5;
+    for (treb_e, mut parts, config_handle) in &mut parts_q {
+        // the sling is sized from the config, so wait for it; a file that failed to load means the defaults
+        if configs.get(&config_handle.0).is_none() && !assets.load_state(&config_handle.0).is_failed() {
+            continue;
+        }
+        let Ok(arm_t) = arm_q.get(parts.arm) else {
+            continue;
+        };
+        let cfg = TrebuchetConfig::resolve(&configs, config_handle);
+
+        cmd.entity(parts.arm).insert(RigidBody::Dynamic);
+        cmd.entity(parts.pivot).insert(RigidBody::Static);
+        cmd.entity(parts.cw).insert(RigidBody::Dynamic);
+        rig(&mut cmd, &mut meshes, &mut materials, treb_e, &mut parts, arm_t, cfg);
+
+        // BAR ==========================================================================
+        cmd.entity(parts.bar)
+        .insert((
+            RigidBody::Static,
+            Collider::cuboid(4., 0.5, 16.)
+        ));
+
+        cmd.entity(treb_e).remove::<NotReady>();
+        // info!("Trebuchet ready");
+    }
+}
+
+// ---
+
+/// Everything that follows the config: arm and counterweight masses, the pivot and
+/// counterweight joints and the sling, laid out from the arm's current pose.
+fn rig(
+    cmd: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    treb_e: Entity,
+    parts: &mut Parts,
+    arm_t: &Transform,
+    cfg: &TrebuchetConfig,
+) {
+    cmd.entity(parts.arm).insert(
+        MassPropertiesBundle::from_shape(&Collider::cuboid(cfg.arm_dim.x, cfg.arm_dim.y, cfg.arm_dim.z), 1.)
+    );
+    let anchor_arm = Vec3::Z *  cfg.arm_dim.z * 0.5;
 
     // PIVOT ===========================================================
 
-    cmd.entity(parts.pivot).insert(RigidBody::Static);
     let joint_id = cmd.spawn((
         RevoluteJoint::new(parts.pivot, parts.arm)
         .with_aligned_axis(Vec3::X)
-        .with_local_anchor_2(-Vec3::Z * PIVOT_OFFSET)
-        .with_angular_velocity_damping(PIVOT_DAMPING)
+        .with_local_anchor_2(-Vec3::Z * cfg.pivot_offset)
+        .with_angular_velocity_damping(cfg.pivot_damping)
         ,
     )).id();
 
     cmd.entity(treb_e).add_child(joint_id);
+    parts.pivot_joint = joint_id;
+    parts.rig.push(joint_id);
 
     // CW ==============================================================
 
-    cmd.entity(parts.cw)
-    .insert((
-        RigidBody::Dynamic,
-        // RigidBody::Static,
-        MassPropertiesBundle::from_shape(&Collider::cylinder(4., 2.), CW_DENSITY)
-    ));
+    cmd.entity(parts.cw).insert(
+        MassPropertiesBundle::from_shape(&Collider::cylinder(4., 2.), cfg.cw_density)
+    );
             
     let joint_id = cmd.spawn(
         RevoluteJoint::new(parts.arm, parts.cw)
@@ -213,28 +349,29 @@ fn setup(
     ).id();
 
     cmd.entity(treb_e).add_child(joint_id);
+    parts.rig.push(joint_id);
 
     // SLING ============================================================
 
-    let arm_pos = arm_q.get(parts.arm).unwrap().translation;
-    let element_dim = Vec3::new(0.1, 0.1, SLING_LEN / SLING_ELEMENT_COUNT as f32);
+    let element_dim = Vec3::new(0.1, 0.1, cfg.sling_len / cfg.sling_element_count.max(1) as f32);
     let anchor_element = Vec3::Z * element_dim.z / 2.;
     let element_mesh = meshes.add(Cuboid::from_size(element_dim));
     let element_mat = materials.add(Color::BLACK);
 
-    let mut pos = arm_pos +  anchor_arm + anchor_element; 
+    // straight out of the arm's short end, whichever way the arm points now
+    let mut pos = arm_t.translation + arm_t.rotation * (anchor_arm + anchor_element);
     let mut prev_element_id = parts.arm;
         
 
-    for i in 0 .. SLING_ELEMENT_COUNT {
+    for i in 0 .. cfg.sling_element_count {
         let element_id = cmd.spawn((
             Mesh3d(element_mesh.clone()),
             MeshMaterial3d(element_mat.clone()),
-            Transform::from_translation(pos),
+            Transform::from_translation(pos).with_rotation(arm_t.rotation),
             RigidBody::Dynamic,
             MassPropertiesBundle::from_shape(
                 &Collider::cuboid(element_dim.x, element_dim.y, element_dim.z), 
-            SLING_ELEMENT_DENSITY),
+            cfg.sling_element_density),
         )).id();
         cmd.entity(treb_e).add_child(element_id);
 
@@ -245,9 +382,10 @@ fn setup(
         ).id();
         
         cmd.entity(treb_e).add_child(joint_id);
+        parts.rig.extend([element_id, joint_id]);
 
         prev_element_id  = element_id;
-        pos += 2. * anchor_element;
+        pos += arm_t.rotation * (2. * anchor_element);
     }
 
     // ENDING =======================================================================
@@ -273,20 +411,37 @@ fn setup(
     ).id();
 
     cmd.entity(treb_e).add_child(joint_id);
+    parts.rig.extend([ending_id, joint_id]);
 
     parts.se = ending_id;    
+}
 
-    // BAR ==========================================================================
-    cmd.entity(parts.bar)
-    .insert((
-        RigidBody::Static,
-        Collider::cuboid(4., 0.5, 16.)
-    ));
+// ---
 
-    cmd.entity(treb_e).remove::<NotReady>();
-    // info!("Trebuchet ready");
-    
-} 
+fn rerig(
+    mut treb_q: Query<
+        (Entity, &mut Parts, &TrebuchetConfigHandle),
+        (With<Rerig>, Without<NotReady>, Without<StateTension>, Without<StateArming>, Without<StateLoose>)
+    >,
+    arm_q: Query<&Transform, With<Arm>>,
+    configs: Res<Assets<TrebuchetConfig>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials : ResMut<Assets<StandardMaterial>>,
+    mut cmd: Commands,
+) {
+    for (treb_e, mut parts, config_handle) in &mut treb_q {
+        let Ok(arm_t) = arm_q.get(parts.arm) else {
+            continue;
+        };
+        for e in parts.rig.drain(..) {
+            cmd.entity(e).despawn_recursive();
+        }
+        let cfg = TrebuchetConfig::resolve(&configs, config_handle);
+        rig(&mut cmd, &mut meshes, &mut materials, treb_e, &mut parts, arm_t, cfg);
+        cmd.entity(treb_e).remove::<Rerig>();
+        info!("Trebuchet {treb_e} rebuilt from its changed config");
+    }
+}
 
 // ---
 
@@ -303,11 +458,26 @@ fn start_game(
 
 fn enter_idle(
     trigger: Trigger<OnAdd, StateIdle>,
+    treb_q: Query<(&TrebuchetConfigHandle, Option<&FirstThrow>)>,
+    configs: Res<Assets<TrebuchetConfig>>,
+    mut rng: ResMut<SiegeRng>,
     mut cmd: Commands,
 ) {
     // info!("Trebuchet entered idle");
+    let Ok((config_handle, first_throw)) = treb_q.get(trigger.entity()) else {
+        return;
+    };
+    let delay = match first_throw {
+        Some(first) => {
+            cmd.entity(trigger.entity()).remove::<FirstThrow>();
+            Duration::from_secs_f32(first.0.max(0.))
+        },
+        None => {
+            Duration::from_secs(rng.between(TrebuchetConfig::resolve(&configs, config_handle).reload_secs))
+        },
+    };
     cmd.entity(trigger.entity()).insert(
-        Interval(Timer::new(Duration::from_secs(fastrand::u64(5..10)), TimerMode::Once))
+        Interval(Timer::new(delay, TimerMode::Once))
     );
 }
 
@@ -337,22 +507,27 @@ fn enter_tension(
 // ---
 
 fn do_tension(
-    treb_q: Query<(Entity, &Parts), (With<Trebuchet>, With<StateTension>)>,
+    treb_q: Query<(Entity, &Parts, &TrebuchetConfigHandle, Has<Commanded>, Has<ArmNow>), (With<Trebuchet>, With<StateTension>)>,
     arm_q: Query<&Transform, With<Arm>>,
     mut link_q: Query<&mut DistanceJoint, With<Link>>,
+    configs: Res<Assets<TrebuchetConfig>>,
     mut cmd: Commands,
 ) {
-    for (treb_e, treb_parts)  in treb_q.iter() {
+    for (treb_e, treb_parts, config_handle, commanded, arm_now)  in treb_q.iter() {
         let Ok(arm_t) = arm_q.get(treb_parts.arm) else {
             continue;
         };
+        let cfg = TrebuchetConfig::resolve(&configs, config_handle);
         
-        let arm_long_end_y = (arm_t.translation - arm_t.forward() * ARM_DIM.z * 0.5).y;
-        if arm_long_end_y  < ARM_LONG_END_Y {
-            cmd.entity(treb_e)
-            .remove::<StateTension>()
-            .insert(StateArming)
-            ;
+        let arm_long_end_y = (arm_t.translation - arm_t.forward() * cfg.arm_dim.z * 0.5).y;
+        if arm_long_end_y  < cfg.arm_long_end_y {
+            // a commanded arm stays down until the player loads it
+            if !commanded || arm_now {
+                cmd.entity(treb_e)
+                .remove::<(StateTension, ArmNow)>()
+                .insert(StateArming)
+                ;
+            }
             // info!("trebuchet {:?} exited tension  ", treb_e);
             continue;
         }
@@ -389,20 +564,24 @@ fn do_tension(
 fn enter_arming(
     trigger: Trigger<OnAdd, StateArming>,
     mut cmd: Commands,
-    treb_q: Query<&Transform>,
+    mut treb_q: Query<(&Transform, Option<&mut Ammo>)>,
 ) {
     let treb_e = trigger.entity();
 
-    let Ok(t) = treb_q.get(treb_e) else {
+    let Ok((t, ammo)) = treb_q.get_mut(treb_e) else {
         return;
     };
+    if let Some(mut ammo) = ammo {
+        ammo.0 = ammo.0.saturating_sub(1);
+    }
 
     cmd.trigger(ProjectleSpawn{
-        key: ProjectleKey::Ball,
-        pos: t.translation.with_y(5.) - Vec3::Z * 14.,
+        key: ProjectleKey::BALL,
+        pos: t.translation.with_y(5.) + t.forward() * 14.,
         dir: None,
         impulse: None,
-        lifetime: None
+        lifetime: None,
+        owner: Some(treb_e)
     });
     
 }
@@ -413,8 +592,10 @@ fn do_arming(
     mut collision_events: EventReader<CollisionEnded>,
     se_q: Query<Entity, With<SlingEnd>>,
     ball_q: Query<Entity, (With<Ball>, Without<Targetable>)>,
+    key_q: Query<&ProjectleKey>,
     parent_q: Query<&Parent>,
     parts_q: Query<&Parts>,
+    registry: Res<ProjectleRegistry>,
     mut cmd: Commands
 
 ) {
@@ -430,11 +611,14 @@ fn do_arming(
                     let Some(link_e) = parts.link else {
                         continue;
                     };
+                    let radius = key_q.get(ball_e).ok()
+                        .and_then(|k| registry.get(k))
+                        .map_or(BALL_RADIUS, |def| def.radius);
 
                     cmd.entity(link_e)
                     .insert(
                         DistanceJoint::new(se_e, ball_e)
-                        .with_rest_length(BALL_RADIUS * 2.)
+                        .with_rest_length(radius * 2.)
                         .with_compliance(0.001)
                         .with_linear_velocity_damping(1000.)
                     );
@@ -460,13 +644,19 @@ fn do_arming(
 // ---
 
 fn do_loose(
-    mut treb_q: Query<(Entity, &mut Parts, &Transform), (With<Trebuchet>, With<StateLoose>)>,
+    mut treb_q: Query<
+        (Entity, &mut Parts, &Transform, &TrebuchetConfigHandle, Option<&ReleaseDot>, Has<Commanded>, Has<ReleaseNow>),
+        (With<Trebuchet>, With<StateLoose>)
+    >,
     mut cmd: Commands,
     se_q: Query<&GlobalTransform>,
-    link_q: Query<&DistanceJoint>
+    link_q: Query<&DistanceJoint>,
+    configs: Res<Assets<TrebuchetConfig>>,
+    mut rng: ResMut<SiegeRng>,
 ) {
 
-    for (treb_e, mut treb_parts, treb_t)  in treb_q.iter_mut() {
+    for (treb_e, mut treb_parts, treb_t, config_handle, release, commanded, release_now)  in treb_q.iter_mut() {
+        let cfg = TrebuchetConfig::resolve(&configs, config_handle);
         
         let Ok(se_t) = se_q.get(treb_parts.se) else {
             continue;
@@ -482,16 +672,18 @@ fn do_loose(
         let center = treb_t.translation  + Vec3::Y * TREBUCHET_DIM.y * 0.5;
         let to_se = (se_t.translation() - center).normalize();
         let dot = to_se.dot(Vec3::Y);
-        if dot > UNHOOKING_DOT {
+        let let_go = if commanded {release_now} else {dot > release.map_or(cfg.unhooking_dot, |r| r.0)};
+        if let_go {
             cmd.entity(link_j.entity2).insert((
                 Targetable,
                 Released,
-                LifeTime(Timer::new(Duration::from_secs(fastrand::u64(15..20)), TimerMode::Once))
+                Thrown,
+                LifeTime(Timer::new(Duration::from_secs(rng.between(cfg.ball_lifetime_secs)), TimerMode::Once))
             ));
 
             cmd.entity(link).despawn();
             cmd.entity(treb_e)
-            .remove::<StateLoose>()
+            .remove::<(StateLoose, ReleaseNow)>()
             .insert(StateIdle)
             ;
             treb_parts.link = None;
@@ -502,13 +694,21 @@ fn do_loose(
 // ---
 
 fn reload(
-    mut t_q: Query<(Entity, &mut Interval), With<Trebuchet>>,
+    mut t_q: Query<
+        (Entity, &mut Interval, Option<&Ammo>, &Transform, Option<&TrebuchetTarget>, Has<HoldFire>),
+        (With<Trebuchet>, Without<Commanded>)
+    >,
     mut cmd: Commands,
     time: Res<Time>
 ) {
-    for (e, mut interval) in & mut t_q {
+    for (e, mut interval, ammo, t, target, held) in & mut t_q {
+        if ammo.is_some_and(|a| a.0 == 0) {
+            continue;
+        }
         interval.0.tick(time.delta());
-        if interval.0.finished() {
+        let aimed = target.and_then(|target| aim_rotation(t, target.0))
+            .is_none_or(|r| t.rotation.angle_between(r) <= AIM_TOLERANCE);
+        if interval.0.finished() && aimed && !held {
             cmd.entity(e)
             .remove::<StateIdle>()
             .remove::<Interval>()
@@ -520,3 +720,102 @@ fn reload(
 
 // ---
 
+fn aim(
+    mut treb_q: Query<
+        (Entity, &mut Transform, &TrebuchetTarget, &TrebuchetConfigHandle, Option<&mut ReleaseDot>),
+        (With<Trebuchet>, With<StateIdle>)
+    >,
+    children_q: Query<&Children>,
+    mut body_q: Query<(&mut Position, &mut Rotation, Option<&mut LinearVelocity>), Without<Trebuchet>>,
+    configs: Res<Assets<TrebuchetConfig>>,
+    range_table: Res<RangeTable>,
+    time: Res<Time>,
+    mut cmd: Commands,
+) {
+    for (treb_e, mut t, target, config_handle, release) in &mut treb_q {
+        let Some(desired) = aim_rotation(&t, target.0) else {
+            continue;
+        };
+        let angle = t.rotation.angle_between(desired);
+        if angle > AIM_TOLERANCE {
+            // turn the whole machine around its base, bodies included, so joints stay intact
+            let step = (AIM_YAW_SPEED * time.delta_secs() / angle).min(1.);
+            let next = t.rotation.slerp(desired, step);
+            let delta = next * t.rotation.inverse();
+            let base = t.translation;
+            t.rotation = next;
+            for c in children_q.iter_descendants(treb_e) {
+                let Ok((mut pos, mut rot, vel)) = body_q.get_mut(c) else {
+                    continue;
+                };
+                pos.0 = base + delta * (pos.0 - base);
+                rot.0 = delta * rot.0;
+                if let Some(mut vel) = vel {
+                    vel.0 = delta * vel.0;
+                }
+            }
+        }
+
+        let cfg = TrebuchetConfig::resolve(&configs, config_handle);
+        let range = (target.0 - t.translation).with_y(0.).length();
+        let Some(dot) = range_table.release_for(cfg, range) else {
+            continue;
+        };
+        match release {
+            Some(mut release) => {
+                release.set_if_neq(ReleaseDot(dot));
+            },
+            None => {
+                cmd.entity(treb_e).insert(ReleaseDot(dot));
+            },
+        }
+    }
+}
+
+// ---
+
+fn apply_range_table(
+    mut events: EventReader<AssetEvent<RangeTable>>,
+    tables: Res<Assets<RangeTable>>,
+    handle: Res<RangeTableHandle>,
+    mut range_table: ResMut<RangeTable>,
+) {
+    for event in events.read() {
+        if event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0) {
+            if let Some(table) = tables.get(&handle.0) {
+                *range_table = table.clone();
+            }
+        }
+    }
+}
+
+// ---
+
+/// Yaw-only rotation that points the trebuchet's forward at `target`.
+fn aim_rotation(t: &Transform, target: Vec3) -> Option<Quat> {
+    let to_target = (target - t.translation).with_y(0.);
+    if to_target.length_squared() < 1. {
+        return None;
+    }
+    Some(Transform::IDENTITY.looking_to(to_target, Vec3::Y).rotation)
+}
+
+// ---
+
+fn apply_config(
+    mut events: EventReader<AssetEvent<TrebuchetConfig>>,
+    treb_q: Query<(Entity, &TrebuchetConfigHandle), (With<Trebuchet>, Without<NotReady>)>,
+    mut cmd: Commands,
+) {
+    // trebuchets still loading build from the file as it is, see `setup`
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        for (treb_e, config_handle) in &treb_q {
+            if config_handle.0.id() == *id {
+                cmd.entity(treb_e).insert(Rerig);
+            }
+        }
+    }
+}
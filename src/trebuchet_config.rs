@@ -0,0 +1,66 @@
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
+use serde::{Deserialize, Serialize};
+
+// ---
+
+/// Tunable trebuchet parameters, loaded from `*.treb.ron` files.
+/// Fields missing in the file keep their default value.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrebuchetConfig {
+    pub arm_dim: Vec3,
+    pub cw_density: f32,
+    pub pivot_damping: f32,
+    pub pivot_offset: f32,
+    pub arm_long_end_y: f32,
+    pub sling_element_density: f32,
+    pub sling_element_count: u32,
+    pub sling_len: f32,
+    pub unhooking_dot: f32,
+    /// Whole seconds, drawn between both ends included.
+    pub reload_secs: (u64, u64),
+    /// Whole seconds, drawn between both ends included.
+    pub ball_lifetime_secs: (u64, u64),
+}
+
+pub const DEFAULT_CONFIG: TrebuchetConfig = TrebuchetConfig {
+    arm_dim: Vec3::new(1., 1., 15.),
+    cw_density: 9.5,
+    pivot_damping: 0.1,
+    pivot_offset: 15. * 0.3,
+    arm_long_end_y: 1.0,
+    sling_element_density: 100.,
+    sling_element_count: 8,
+    sling_len: 15. * 0.75,
+    unhooking_dot: 0.99,
+    reload_secs: (5, 10),
+    ball_lifetime_secs: (15, 20),
+};
+
+impl Default for TrebuchetConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+impl TrebuchetConfig {
+    /// Config behind the handle, or the built-in defaults while it is still loading.
+    pub fn resolve<'a>(configs: &'a Assets<TrebuchetConfig>, handle: &TrebuchetConfigHandle) -> &'a TrebuchetConfig {
+        configs.get(&handle.0).unwrap_or(&DEFAULT_CONFIG)
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct TrebuchetConfigHandle(pub Handle<TrebuchetConfig>);
+
+#[derive(Resource)]
+pub struct DefaultTrebuchetConfig(pub Handle<TrebuchetConfig>);
+
+/// Per-trebuchet config files, keyed by the trebuchet index in the formation.
+#[derive(Resource, Default)]
+pub struct TrebuchetConfigOverrides(pub HashMap<usize, String>);
+
+pub const DEFAULT_CONFIG_PATH: &str = "configs/default.treb.ron";
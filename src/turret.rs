@@ -1,17 +1,24 @@
-use std::f32::consts::PI;
 // use avian3d::parry::na::distance_squared;
+use avian3d::prelude::{Gravity, LinearVelocity};
 use bevy::{prelude::*, scene::SceneInstanceReady};
-use crate::shared::Targetable;
-use crate::{radar::RadarPositions, shared::SetTarget};
-use crate::projectle::{ProjectleKey, ProjectleSpawn};
+use crate::ballistics::{disperse, intercept};
+use crate::shared::{Headless, Targetable};
+use crate::rng::SiegeRng;
+use crate::GameState;
+use crate::game::MatchClock;
+use crate::radar::RadarTracks;
+use crate::projectle::{ProjectleKey, ProjectleRegistry, ProjectleSpawn};
+use crate::tracer::{Tracer, TRACER_LIFETIME};
+use crate::turret_supply::{after_burst, AmmoStore, BarrelHeat, Magazine, Reloading};
+use serde::{Deserialize, Serialize};
 pub struct TurretPlugin;
 impl Plugin for TurretPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(Update, spawn.run_if(resource_added::<RadarPositions>))
-        .add_systems(Update, follow.run_if(any_with_component::<Target>))
-        .add_systems(Update, fire.run_if(any_with_component::<Fire>))
-        .add_observer(set_target)
+        .init_resource::<DefaultFireMode>()
+        .add_systems(FixedUpdate, follow.run_if(any_with_component::<Target>).run_if(in_state(GameState::Siege)))
+        .add_systems(FixedUpdate, fire.run_if(any_with_component::<Fire>).run_if(in_state(GameState::Siege)))
+        .add_observer(attach)
         .add_observer(ball_despawn)
         .add_observer(clear_target)
         ;
@@ -27,10 +34,49 @@ pub struct Barrel;
 pub struct Turret;
 
 #[derive(Component)]
-pub struct BarrelTurret(Entity);
+pub struct BarrelTurret(pub Entity);
 
+/// Radars whose tracks a turret engages; any radar's when empty.
+#[derive(Component, Default)]
+pub struct FedBy(pub Vec<Entity>);
+
+/// What a turret type brings to the field, handed to its barrels as they are set up.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurretSpec {
+    /// `DefaultFireMode` if not set.
+    pub fire_mode: Option<FireMode>,
+    pub dispersion: f32,
+    pub ammo: AmmoStore,
+    pub magazine: Magazine,
+    pub heat: BarrelHeat,
+}
+
+impl Default for TurretSpec {
+    fn default() -> Self {
+        Self {
+            fire_mode: None,
+            dispersion: DEFAULT_DISPERSION,
+            ammo: AmmoStore::default(),
+            magazine: Magazine::default(),
+            heat: BarrelHeat::default(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Target(pub Entity);
+
+/// Aimed and fired by the player instead of fire control.
 #[derive(Component)]
-pub struct Target(Entity);
+pub struct Manned;
+
+/// A barrel fired a burst of `rounds` bullets or tracers.
+#[derive(Event)]
+pub struct Burst {
+    pub barrel: Entity,
+    pub rounds: usize,
+}
 
 
 #[derive(Component)]
@@ -40,28 +86,83 @@ pub struct LastShoot(f32);
 #[component(storage = "SparseSet")]
 pub struct Fire;
 
+/// Angular dispersion of each burst around the barrel axis, standard deviation in radians.
+#[derive(Component)]
+pub struct AimError {
+    pub dispersion: f32
+}
+
+impl Default for AimError {
+    fn default() -> Self {
+        Self { dispersion: DEFAULT_DISPERSION }
+    }
+}
+
+/// How a barrel's bursts are simulated.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub enum FireMode {
+    /// A line of dynamic bullet spheres.
+    #[default]
+    Spheres,
+    /// Ray-cast tracers, see `TracerPlugin`.
+    Tracers,
+}
+
+/// `FireMode` given to barrels as they are set up.
+#[derive(Resource, Default)]
+pub struct DefaultFireMode(pub FireMode);
+
 const COOLDOWN_TIME: f32 = 0.5;
-const OPERATOR_DRUNK_DEGREE: f32 = 0.3;
+const DEFAULT_DISPERSION: f32 = 0.01;
+const MUZZLE_IMPULSE: f32 = 1000.;
+/// Distance along the barrel where the first bullet of a burst appears.
+pub const MUZZLE_OFFSET: f32 = 15.;
+/// Tracers per burst, each with its own dispersion.
+const TRACER_ROUNDS: usize = 4;
+/// Bullet spheres per burst, in a line along the barrel.
+const SPHERE_ROUNDS: usize = 12;
+
+/// Bullet speed right after `fire` applies `MUZZLE_IMPULSE`.
+pub fn muzzle_speed(registry: &ProjectleRegistry) -> f32 {
+    registry.get(&ProjectleKey::BULLET).map_or(0., |def| MUZZLE_IMPULSE / def.mass())
+}
 
 // ---
 
-fn spawn (
+/// Gives a freshly placed turret its model, or a bare barrel when headless.
+fn attach(
+    tr: Trigger<OnAdd, Turret>,
+    headless: Option<Res<Headless>>,
+    assets: Res<AssetServer>,
+    spec_q: Query<&TurretSpec>,
+    fire_mode: Res<DefaultFireMode>,
     mut cmd: Commands,
-    assets: ResMut<AssetServer>,
-    rp: Res<RadarPositions>
 ) {
-    let sh = assets.load(GltfAssetLabel::Scene(0).from_asset("models/turret.glb"));
-    for p in &rp.0  {
-        let pos = *p + Vec3::X * -20. * p.x.signum(); 
-        cmd.spawn((
-            SceneRoot(sh.clone()),
-            Transform::from_translation(pos).with_rotation(Quat::from_rotation_y(PI)),
-            Turret,
-        ))
+    let turret_e = tr.entity();
+    if headless.is_none() {
+        cmd.entity(turret_e)
+        .insert(SceneRoot(assets.load(GltfAssetLabel::Scene(0).from_asset("models/turret.glb"))))
         .observe(setup)
         ;
-    
+        return;
     }
+    let spec = spec_q.get(turret_e).cloned().unwrap_or_default();
+    let barrel_e = cmd.spawn((
+        Transform::from_xyz(0., 6., 0.),
+        barrel(turret_e, &spec, &fire_mode),
+    ))
+    .id()
+    ;
+    cmd.entity(turret_e).add_child(barrel_e);
+}
+
+// ---
+
+/// Whether a barrel at `barrel_pos`, on a turret facing `facing`, can reach a target at
+/// `target_pos`: only ahead of the turret and above the barrel.
+pub fn can_engage(facing: Vec3, barrel_pos: Vec3, target_pos: Vec3) -> bool {
+    let offset = target_pos - barrel_pos;
+    offset.with_y(0.).dot(facing.with_y(0.)) >= 0. && offset.y >= 0.
 }
 
 // ---
@@ -71,42 +172,33 @@ fn setup (
     mut cmd: Commands,
     props_q: Query<&GltfExtras>,
     children_q: Query<&Children>,
+    spec_q: Query<&TurretSpec>,
+    fire_mode: Res<DefaultFireMode>,
 ) {
     let turret_e = tr.entity();
+    let spec = spec_q.get(turret_e).cloned().unwrap_or_default();
     for c in children_q.iter_descendants(turret_e) {
         let Ok(props) = props_q.get(c) else {continue};
 
         if props.value.contains("Barrel") {
-            cmd.entity(c).insert((Barrel, LastShoot(0.), BarrelTurret(turret_e)));
+            cmd.entity(c).insert(barrel(turret_e, &spec, &fire_mode));
         }
     }
 }
 
-// ---
-
-fn set_target(
-    tr: Trigger<SetTarget>,
-    target_q: Query<&Transform, Without<Turret>>,
-    barrel_q: Query<(Entity, &GlobalTransform), (With<Barrel>, Without<Target>)>,
-    mut cmd: Commands
-) {
-    let Ok(Transform{translation: target_pos , ..}) = target_q.get(tr.event().0) else {
-        return;
-    };
-    
-    let Some(barrel_e) = barrel_q.iter().min_by(|a , b| {
-        a.1.translation().distance_squared(*target_pos).total_cmp(
-            &b.1.translation().distance_squared(*target_pos)
-        )
-    }).map(|v| v.0) else {
-        // println!("return");
-        return;
-    };
-    // println!("set target pos {:?}", target_pos);
-    cmd.entity(barrel_e).insert(Target(tr.event().0));
+fn barrel(turret_e: Entity, spec: &TurretSpec, fire_mode: &DefaultFireMode) -> impl Bundle {
+    (
+        Barrel,
+        LastShoot(0.),
+        BarrelTurret(turret_e),
+        AimError { dispersion: spec.dispersion },
+        spec.fire_mode.unwrap_or(fire_mode.0),
+        spec.magazine,
+        spec.heat,
+    )
 }
 
-// --=
+// ---
 
 fn clear_target(
     tr: Trigger<OnRemove, Target>,
@@ -118,37 +210,61 @@ fn clear_target(
 // ---
 
 fn follow(
-    target_q: Query<&Transform, Without<Barrel>>,
+    target_q: Query<(&Transform, Option<&LinearVelocity>), Without<Barrel>>,
+    tracks: Res<RadarTracks>,
+    clock: Res<MatchClock>,
     turret_q: Query<&GlobalTransform, (Without<Target>, Without<Barrel>)>,
     mut barrel_q: Query<(&mut Transform, &GlobalTransform, &Target, &BarrelTurret, Entity), (With<Target>, With<Barrel>)>,
     // mut gizmos: Gizmos,
+    gravity: Res<Gravity>,
+    registry: Res<ProjectleRegistry>,
     time: Res<Time>,
     mut cmd: Commands
 ) {
     for (mut barrel_trans, barrel_trans_g, target, barrel_turret, barrel_e) in &mut barrel_q {
-        let Ok(Transform{translation: mut target_pos, ..}) = target_q.get(target.0) else {
+        let Ok((Transform{translation: true_pos, ..}, true_vel)) = target_q.get(target.0) else {
             cmd.entity(barrel_e).remove::<Target>();
             continue;
         };
+        // aim at the radar's estimate once it has one, the true state is only a fallback
+        let estimate = tracks.get(target.0)
+            .filter(|t| t.history.len() > 1)
+            .and_then(|t| Some((t.position_at(clock.elapsed, gravity.0)?, t.velocity + gravity.0 * (clock.elapsed - t.last_seen()))));
+        let (target_pos, target_vel) = match estimate {
+            Some((pos, vel)) => (pos, Some(vel)),
+            None => (*true_pos, true_vel.map(|v| v.0)),
+        };
 
-        target_pos *= fastrand::f32() * OPERATOR_DRUNK_DEGREE  + 1.; 
+        let Ok(turret_trans_g) = turret_q.get(barrel_turret.0) else {
+            continue;
+        };
 
-        if target_pos.z < barrel_trans_g.translation().z ||  target_pos.y < barrel_trans_g.translation().y {
+        if !can_engage(*turret_trans_g.forward(), barrel_trans_g.translation(), target_pos) {
             cmd.entity(barrel_e).remove::<Target>();
             continue;
         }
-        
-        let Ok(turret_trans_g) = turret_q.get(barrel_turret.0) else {
+
+        // target balls fly free, so both they and the bullets only feel gravity
+        let target_accel = if target_vel.is_some() {gravity.0} else {Vec3::ZERO};
+        let muzzle = barrel_trans_g.translation() + barrel_trans_g.forward() * MUZZLE_OFFSET;
+        let Some((aim_pos, _)) = intercept(
+            muzzle, 
+            muzzle_speed(&registry), 
+            target_pos, 
+            target_vel.unwrap_or(Vec3::ZERO), 
+            target_accel - gravity.0
+        ) else {
+            cmd.entity(barrel_e).remove::<Fire>();
             continue;
         };
 
-        let rotation_to_target = barrel_trans_g.compute_transform().looking_at(target_pos, Vec3::Y).rotation;
+        let rotation_to_target = barrel_trans_g.compute_transform().looking_at(aim_pos, Vec3::Y).rotation;
         let turret_rotation = turret_trans_g.compute_transform().rotation;
         barrel_trans.rotation = barrel_trans.rotation.slerp(
             turret_rotation.inverse().mul_quat(rotation_to_target), 
             time.delta_secs() * 50.
         );
-        if barrel_trans_g.forward().dot((target_pos - barrel_trans_g.translation()).normalize()) > 0.95 {
+        if barrel_trans_g.forward().dot((aim_pos - barrel_trans_g.translation()).normalize()) > 0.95 {
             cmd.entity(barrel_e).insert(Fire) ;
             // let distance = target_trans.translation.distance(turret_trans_g.translation());
             // gizmos.ray(barrel_trans_g.translation(), barrel_trans_g.forward() * distance, Color::srgb(1., 1., 0.));
@@ -177,24 +293,55 @@ fn ball_despawn(
 
 fn fire (
     mut cmd: Commands,
-    mut barrel_q: Query<(&GlobalTransform, &mut LastShoot), (With<Fire>, With<Target>)>,
+    mut barrel_q: Query<
+        (Entity, &GlobalTransform, &mut LastShoot, &AimError, &FireMode, &mut Magazine, &mut BarrelHeat),
+        (With<Fire>, Or<(With<Target>, With<Manned>)>, Without<Reloading>)
+    >,
+    mut rng: ResMut<SiegeRng>,
+    registry: Res<ProjectleRegistry>,
     time: Res<Time>
 ) {
 
-    for (gt, mut ls) in barrel_q.iter_mut() {
+    for (barrel_e, gt, mut ls, aim_error, mode, mut magazine, mut heat) in barrel_q.iter_mut() {
         let e_s = time.elapsed_secs();
-        if ls.0 + COOLDOWN_TIME < e_s {
-            for i in 0..12 {
-                cmd.trigger(ProjectleSpawn{
-                    key: ProjectleKey::Bullet,
-                    pos: gt.translation() + gt.forward() * (15. + i as f32),
-                    dir: None,
-                    impulse: Some(gt.forward() * 1000.),
-                    lifetime: Some(2)
-                });
-            }
-            ls.0 = e_s;
+        if ls.0 + COOLDOWN_TIME >= e_s || !magazine.ready(&heat) {
+            continue;
         }
+        // the last burst of a magazine can come up short
+        let rounds = match mode {
+            FireMode::Spheres => SPHERE_ROUNDS,
+            FireMode::Tracers => TRACER_ROUNDS,
+        }.min(magazine.rounds as usize);
+        match mode {
+            FireMode::Spheres => {
+                let dir = disperse(gt.forward(), aim_error.dispersion, &mut rng);
+                for i in 0..rounds {
+                    cmd.trigger(ProjectleSpawn{
+                        key: ProjectleKey::BULLET,
+                        pos: gt.translation() + dir * (MUZZLE_OFFSET + i as f32),
+                        dir: None,
+                        impulse: Some(dir * MUZZLE_IMPULSE),
+                        lifetime: Some(2),
+                        owner: Some(barrel_e)
+                    });
+                }
+            },
+            FireMode::Tracers => {
+                for _ in 0..rounds {
+                    let dir = disperse(gt.forward(), aim_error.dispersion, &mut rng);
+                    cmd.spawn(Tracer::new(
+                        gt.translation() + dir * MUZZLE_OFFSET,
+                        dir * muzzle_speed(&registry),
+                        ProjectleKey::BULLET,
+                        TRACER_LIFETIME,
+                        Some(barrel_e),
+                    ));
+                }
+            },
+        }
+        cmd.trigger(Burst { barrel: barrel_e, rounds });
+        ls.0 = e_s;
+        after_burst(&mut cmd, barrel_e, rounds as u32, &mut magazine, &mut heat);
     }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::fortress::FortressIntegrity;
+use crate::turret::{BarrelTurret, Turret};
+use crate::GameState;
+
+// ---
+
+/// Ammunition and heat for the turrets: every burst takes its rounds from the barrel's magazine
+/// and heats it up, empty magazines reload from the turret's store, and the fortress arsenal
+/// tops the stores up over time. Rounds are single bullets or tracers throughout.
+pub struct TurretSupplyPlugin;
+impl Plugin for TurretSupplyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Arsenal>()
+        .add_systems(FixedUpdate, (cool, reload, resupply).run_if(in_state(GameState::Siege)))
+        ;
+    }
+}
+
+// ---
+
+/// Rounds kept at a turret for its barrels' magazines.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmmoStore {
+    pub rounds: u32,
+    pub capacity: u32,
+}
+
+impl Default for AmmoStore {
+    fn default() -> Self {
+        Self { rounds: 2400, capacity: 2400 }
+    }
+}
+
+/// Rounds loaded in a barrel; a burst fires as many as it has bullets or tracers.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Magazine {
+    pub rounds: u32,
+    pub size: u32,
+    /// Seconds to swap in a full magazine.
+    pub reload_secs: f32,
+}
+
+impl Default for Magazine {
+    fn default() -> Self {
+        Self { rounds: 240, size: 240, reload_secs: 3. }
+    }
+}
+
+/// Present while a barrel is swapping magazines.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Reloading(pub Timer);
+
+/// Barrel temperature from 0 (cold) to 1 (overheated). An overheated barrel stops firing
+/// until it has cooled down to `recover`.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BarrelHeat {
+    pub heat: f32,
+    /// Heat added by each burst.
+    pub per_burst: f32,
+    /// Heat lost per second.
+    pub cooling: f32,
+    pub recover: f32,
+    pub overheated: bool,
+}
+
+impl Default for BarrelHeat {
+    fn default() -> Self {
+        Self { heat: 0., per_burst: 0.08, cooling: 0.1, recover: 0.4, overheated: false }
+    }
+}
+
+impl BarrelHeat {
+    pub fn add_burst(&mut self) {
+        self.heat = (self.heat + self.per_burst).min(1.);
+        if self.heat >= 1. {
+            self.overheated = true;
+        }
+    }
+}
+
+impl Magazine {
+    /// Whether a barrel with this magazine and heat can fire right now.
+    pub fn ready(&self, heat: &BarrelHeat) -> bool {
+        self.rounds > 0 && !heat.overheated
+    }
+}
+
+/// The fortress' reserve, carried out to the turrets' stores at `per_sec` rounds per second.
+/// Deliveries slow down as the fortress is knocked loose and stop once it's gone.
+#[derive(Resource, Debug)]
+pub struct Arsenal {
+    pub rounds: u32,
+    pub per_sec: f32,
+    /// Fraction of a round delivered but not yet counted.
+    carry: f32,
+}
+
+impl Default for Arsenal {
+    fn default() -> Self {
+        Self { rounds: 12000, per_sec: 60., carry: 0. }
+    }
+}
+
+impl Arsenal {
+    pub fn new(rounds: u32, per_sec: f32) -> Self {
+        Self { rounds, per_sec, carry: 0. }
+    }
+}
+
+// ---
+
+/// Takes a burst of `rounds` from the magazine and starts the reload once it's empty.
+pub fn after_burst(cmd: &mut Commands, barrel_e: Entity, rounds: u32, magazine: &mut Magazine, heat: &mut BarrelHeat) {
+    magazine.rounds = magazine.rounds.saturating_sub(rounds);
+    heat.add_burst();
+    if magazine.rounds == 0 {
+        cmd.entity(barrel_e).insert(Reloading(Timer::new(Duration::from_secs_f32(magazine.reload_secs), TimerMode::Once)));
+    }
+}
+
+// ---
+
+fn cool(
+    mut heat_q: Query<&mut BarrelHeat>,
+    time: Res<Time>,
+) {
+    for mut heat in &mut heat_q {
+        heat.heat = (heat.heat - heat.cooling * time.delta_secs()).max(0.);
+        if heat.overheated && heat.heat <= heat.recover {
+            heat.overheated = false;
+        }
+    }
+}
+
+// ---
+
+fn reload(
+    mut barrel_q: Query<(Entity, &mut Magazine, &mut Reloading, &BarrelTurret)>,
+    mut store_q: Query<&mut AmmoStore, With<Turret>>,
+    mut cmd: Commands,
+    time: Res<Time>,
+) {
+    for (barrel_e, mut magazine, mut reloading, barrel_turret) in &mut barrel_q {
+        if !reloading.0.tick(time.delta()).finished() {
+            continue;
+        }
+        let Ok(mut store) = store_q.get_mut(barrel_turret.0) else {
+            continue;
+        };
+        // a layout can hand out magazines holding more than `size`, those need nothing
+        let rounds = magazine.size.saturating_sub(magazine.rounds).min(store.rounds);
+        // an empty store keeps the barrel reloading until resupply arrives
+        if rounds == 0 && magazine.rounds == 0 {
+            continue;
+        }
+        store.rounds -= rounds;
+        magazine.rounds += rounds;
+        cmd.entity(barrel_e).remove::<Reloading>();
+    }
+}
+
+// ---
+
+fn resupply(
+    mut arsenal: ResMut<Arsenal>,
+    mut store_q: Query<&mut AmmoStore, With<Turret>>,
+    integrity: Option<Res<FortressIntegrity>>,
+    time: Res<Time>,
+) {
+    // the rounds are carried out of the fortress, by fewer hands the less of it stands
+    let standing = integrity.map_or(1., |i| 1. - i.destroyed());
+    if arsenal.rounds == 0 || standing <= 0. {
+        return;
+    }
+    let mut needy: Vec<_> = store_q.iter_mut().filter(|s| s.rounds < s.capacity).collect();
+    if needy.is_empty() {
+        arsenal.carry = 0.;
+        return;
+    }
+    arsenal.carry += arsenal.per_sec * standing * time.delta_secs();
+    let whole = arsenal.carry as u32;
+    arsenal.carry -= whole as f32;
+    // emptiest store first, one round at a time
+    for _ in 0..whole.min(arsenal.rounds) {
+        let Some(store) = needy.iter_mut()
+            .filter(|s| s.rounds < s.capacity)
+            .min_by_key(|s| s.rounds)
+        else {
+            break;
+        };
+        store.rounds += 1;
+        arsenal.rounds -= 1;
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world
+    }
+
+    fn step(world: &mut World, secs: f32) {
+        world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(secs));
+    }
+
+    fn barrel(world: &mut World, store: u32) -> (Entity, Entity) {
+        let turret_e = world.spawn((Turret, AmmoStore { rounds: store, capacity: 100 })).id();
+        let magazine = Magazine { rounds: 0, size: 40, reload_secs: 1. };
+        let barrel_e = world.spawn((
+            magazine,
+            Reloading(Timer::from_seconds(magazine.reload_secs, TimerMode::Once)),
+            BarrelTurret(turret_e),
+        )).id();
+        (turret_e, barrel_e)
+    }
+
+    #[test]
+    fn reload_takes_a_magazine_from_the_store() {
+        let mut world = world();
+        let (turret_e, barrel_e) = barrel(&mut world, 100);
+
+        step(&mut world, 0.5);
+        world.run_system_once(reload).unwrap();
+        assert_eq!(world.get::<Magazine>(barrel_e).unwrap().rounds, 0);
+
+        step(&mut world, 0.6);
+        world.run_system_once(reload).unwrap();
+        assert_eq!(world.get::<Magazine>(barrel_e).unwrap().rounds, 40);
+        assert_eq!(world.get::<AmmoStore>(turret_e).unwrap().rounds, 60);
+        assert!(!world.entity(barrel_e).contains::<Reloading>());
+    }
+
+    #[test]
+    fn reload_waits_for_an_empty_store() {
+        let mut world = world();
+        let (turret_e, barrel_e) = barrel(&mut world, 0);
+        step(&mut world, 1.5);
+        world.run_system_once(reload).unwrap();
+        assert!(world.entity(barrel_e).contains::<Reloading>());
+
+        world.get_mut::<AmmoStore>(turret_e).unwrap().rounds = 10;
+        world.run_system_once(reload).unwrap();
+        assert_eq!(world.get::<Magazine>(barrel_e).unwrap().rounds, 10);
+        assert!(!world.entity(barrel_e).contains::<Reloading>());
+    }
+
+    #[test]
+    fn heat_stops_the_barrel_until_it_cools() {
+        let mut world = world();
+        let heat = BarrelHeat { per_burst: 0.5, cooling: 1., recover: 0.2, ..default() };
+        let magazine = Magazine { rounds: 10, ..default() };
+        let barrel_e = world.spawn((heat, magazine)).id();
+
+        let mut heat = world.get_mut::<BarrelHeat>(barrel_e).unwrap();
+        heat.add_burst();
+        heat.add_burst();
+        assert!(!magazine.ready(&heat));
+
+        step(&mut world, 0.5);
+        world.run_system_once(cool).unwrap();
+        assert!(!magazine.ready(world.get::<BarrelHeat>(barrel_e).unwrap()));
+        step(&mut world, 0.4);
+        world.run_system_once(cool).unwrap();
+        assert!(magazine.ready(world.get::<BarrelHeat>(barrel_e).unwrap()));
+    }
+
+    #[test]
+    fn resupply_fills_the_emptiest_store_first() {
+        let mut world = world();
+        world.insert_resource(Arsenal::new(10, 64.));
+        let empty = world.spawn((Turret, AmmoStore { rounds: 0, capacity: 5 })).id();
+        let half = world.spawn((Turret, AmmoStore { rounds: 4, capacity: 5 })).id();
+
+        step(&mut world, 4. / 64.);
+        world.run_system_once(resupply).unwrap();
+        assert_eq!(world.get::<AmmoStore>(empty).unwrap().rounds, 4);
+        assert_eq!(world.get::<AmmoStore>(half).unwrap().rounds, 4);
+        assert_eq!(world.resource::<Arsenal>().rounds, 6);
+    }
+
+    #[test]
+    fn resupply_slows_with_the_fortress() {
+        let mut world = world();
+        world.insert_resource(Arsenal::new(100, 64.));
+        world.insert_resource(FortressIntegrity { total_mass: 100., loose_mass: 50. });
+        let store = world.spawn((Turret, AmmoStore { rounds: 0, capacity: 100 })).id();
+
+        step(&mut world, 8. / 64.);
+        world.run_system_once(resupply).unwrap();
+        assert_eq!(world.get::<AmmoStore>(store).unwrap().rounds, 4);
+
+        world.resource_mut::<FortressIntegrity>().loose_mass = 100.;
+        step(&mut world, 8. / 64.);
+        world.run_system_once(resupply).unwrap();
+        assert_eq!(world.get::<AmmoStore>(store).unwrap().rounds, 4);
+    }
+}